@@ -0,0 +1,409 @@
+//! A plain, externally tagged mirror of [`Config`] for binary formats
+
+use std::{collections::HashSet, time::Duration};
+
+use http::{HeaderName, Method};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders, HeaderCap,
+    MaxAge, Origin, OriginMaxAge, SerdeRegexSet, Vary, CONFIG_VERSION,
+};
+
+/// [`Config`] in a shape that non-self-describing formats such as `bincode`
+/// and `postcard` can read back.
+///
+/// `Config`'s own serde implementation is made for config files: a field can
+/// be a keyword, a string or a list, and the list variants are untagged, so
+/// deserializing has to ask the format what comes next. Binary formats can't
+/// answer that, and reading a `Config` from them fails. This type keeps every
+/// enum externally tagged and every list a plain sequence of strings, sorted
+/// so equal configs encode to equal bytes.
+///
+/// ```
+/// use tower_http_cors_config::{BinaryConfig, Config};
+///
+/// let config = Config::default();
+/// let binary = BinaryConfig::try_from(&config).unwrap();
+/// assert_eq!(Config::try_from(binary).unwrap(), config);
+/// ```
+///
+/// Converting back validates entries the same way deserializing a `Config`
+/// does. [`AllowedOrigins::Dynamic`] and [`AllowedOrigins::Shared`] have no
+/// serialized form, and
+/// `on_origin_decision` is dropped like it is when serializing `Config`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryConfig {
+    pub enabled: bool,
+    pub allow_credentials: bool,
+    pub allowed_headers: BinaryHeaders,
+    pub allowed_methods: BinaryMethods,
+    pub allowed_origins: BinaryOrigins,
+    pub denied_origins: Option<Vec<String>>,
+    pub allow_private_network: bool,
+    pub max_age: BinaryMaxAge,
+    pub max_age_by_origin: Vec<(BinaryOrigins, Duration)>,
+    pub expose_headers: BinaryExposeHeaders,
+    pub vary: Vec<String>,
+}
+
+/// [`MaxAge`] without the keyword-or-duration representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryMaxAge {
+    Disabled,
+    Seconds(Duration),
+    BrowserMax,
+}
+
+impl From<MaxAge> for BinaryMaxAge {
+    fn from(value: MaxAge) -> Self {
+        match value {
+            MaxAge::Disabled => BinaryMaxAge::Disabled,
+            MaxAge::Seconds(max_age) => BinaryMaxAge::Seconds(max_age),
+            MaxAge::BrowserMax => BinaryMaxAge::BrowserMax,
+        }
+    }
+}
+
+impl From<BinaryMaxAge> for MaxAge {
+    fn from(value: BinaryMaxAge) -> Self {
+        match value {
+            BinaryMaxAge::Disabled => MaxAge::Disabled,
+            BinaryMaxAge::Seconds(max_age) => MaxAge::Seconds(max_age),
+            BinaryMaxAge::BrowserMax => MaxAge::BrowserMax,
+        }
+    }
+}
+
+/// [`AllowedOrigins`] without untagged variants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOrigins {
+    Any,
+    Mirror,
+    LocalhostAny,
+    Exact(Vec<String>),
+    Wildcard(Vec<String>),
+    List(Vec<String>),
+}
+
+/// [`AllowedHeaders`] without untagged variants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryHeaders {
+    Any,
+    Mirror,
+    MirrorCapped {
+        max_headers: usize,
+        max_bytes: usize,
+    },
+    MirrorList(Vec<String>),
+    Regex(Vec<String>),
+    List(Vec<String>),
+}
+
+/// [`AllowedMethods`] without untagged variants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryMethods {
+    Any,
+    Mirror,
+    List(Vec<String>),
+}
+
+/// [`ExposeHeaders`] without untagged variants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryExposeHeaders {
+    Any,
+    None,
+    SameAsAllowed,
+    List(Vec<String>),
+}
+
+fn sorted<'a>(items: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut items = items.into_iter().map(String::from).collect::<Vec<_>>();
+    items.sort();
+    items
+}
+
+fn patterns(set: &SerdeRegexSet) -> Vec<String> {
+    sorted(set.patterns().iter().map(String::as_str))
+}
+
+fn header_names(names: Vec<String>) -> Result<HashSet<HeaderName>, ConfigError> {
+    names
+        .into_iter()
+        .map(|name| {
+            HeaderName::try_from(name.as_str()).map_err(|_| ConfigError::InvalidHeaderName(name))
+        })
+        .collect()
+}
+
+fn regex_set(patterns: Vec<String>) -> Result<SerdeRegexSet, ConfigError> {
+    Ok(SerdeRegexSet::new(RegexSet::new(patterns)?)?)
+}
+
+impl TryFrom<&AllowedOrigins> for BinaryOrigins {
+    type Error = ConfigError;
+
+    fn try_from(value: &AllowedOrigins) -> Result<Self, Self::Error> {
+        Ok(match value {
+            AllowedOrigins::Any => BinaryOrigins::Any,
+            AllowedOrigins::Mirror => BinaryOrigins::Mirror,
+            AllowedOrigins::LocalhostAny => BinaryOrigins::LocalhostAny,
+            AllowedOrigins::Dynamic(_) | AllowedOrigins::Shared(_) => {
+                return Err(ConfigError::DynamicOrigins)
+            }
+            AllowedOrigins::Exact(origins) => {
+                BinaryOrigins::Exact(sorted(origins.iter().map(String::as_str)))
+            }
+            AllowedOrigins::Wildcard(patterns) => {
+                BinaryOrigins::Wildcard(sorted(patterns.iter().map(String::as_str)))
+            }
+            AllowedOrigins::List(set) => BinaryOrigins::List(patterns(set)),
+        })
+    }
+}
+
+impl TryFrom<BinaryOrigins> for AllowedOrigins {
+    type Error = ConfigError;
+
+    fn try_from(value: BinaryOrigins) -> Result<Self, Self::Error> {
+        Ok(match value {
+            BinaryOrigins::Any => AllowedOrigins::Any,
+            BinaryOrigins::Mirror => AllowedOrigins::Mirror,
+            BinaryOrigins::LocalhostAny => AllowedOrigins::LocalhostAny,
+            BinaryOrigins::Exact(origins) => {
+                for origin in &origins {
+                    Origin::parse(origin)?;
+                }
+                AllowedOrigins::Exact(origins.into_iter().collect())
+            }
+            BinaryOrigins::Wildcard(patterns) => {
+                AllowedOrigins::Wildcard(patterns.into_iter().collect())
+            }
+            BinaryOrigins::List(patterns) => AllowedOrigins::List(regex_set(patterns)?),
+        })
+    }
+}
+
+impl From<&AllowedHeaders> for BinaryHeaders {
+    fn from(value: &AllowedHeaders) -> Self {
+        let names = |names: &HashSet<HeaderName>| sorted(names.iter().map(HeaderName::as_str));
+        match value {
+            AllowedHeaders::Any => BinaryHeaders::Any,
+            AllowedHeaders::Mirror => BinaryHeaders::Mirror,
+            &AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers,
+                max_bytes,
+            }) => BinaryHeaders::MirrorCapped {
+                max_headers,
+                max_bytes,
+            },
+            AllowedHeaders::MirrorList(headers) => BinaryHeaders::MirrorList(names(headers)),
+            AllowedHeaders::Regex(headers) => BinaryHeaders::Regex(patterns(headers)),
+            AllowedHeaders::List(headers) => BinaryHeaders::List(names(headers)),
+        }
+    }
+}
+
+impl TryFrom<BinaryHeaders> for AllowedHeaders {
+    type Error = ConfigError;
+
+    fn try_from(value: BinaryHeaders) -> Result<Self, Self::Error> {
+        Ok(match value {
+            BinaryHeaders::Any => AllowedHeaders::Any,
+            BinaryHeaders::Mirror => AllowedHeaders::Mirror,
+            BinaryHeaders::MirrorCapped {
+                max_headers,
+                max_bytes,
+            } => AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers,
+                max_bytes,
+            }),
+            BinaryHeaders::MirrorList(names) => AllowedHeaders::MirrorList(header_names(names)?),
+            BinaryHeaders::Regex(patterns) => AllowedHeaders::Regex(regex_set(patterns)?),
+            BinaryHeaders::List(names) => AllowedHeaders::List(header_names(names)?),
+        })
+    }
+}
+
+impl From<&AllowedMethods> for BinaryMethods {
+    fn from(value: &AllowedMethods) -> Self {
+        match value {
+            AllowedMethods::Any => BinaryMethods::Any,
+            AllowedMethods::Mirror => BinaryMethods::Mirror,
+            AllowedMethods::List(methods) => {
+                BinaryMethods::List(sorted(methods.iter().map(Method::as_str)))
+            }
+        }
+    }
+}
+
+impl TryFrom<BinaryMethods> for AllowedMethods {
+    type Error = ConfigError;
+
+    fn try_from(value: BinaryMethods) -> Result<Self, Self::Error> {
+        Ok(match value {
+            BinaryMethods::Any => AllowedMethods::Any,
+            BinaryMethods::Mirror => AllowedMethods::Mirror,
+            BinaryMethods::List(methods) => AllowedMethods::List(
+                methods
+                    .into_iter()
+                    .map(|method| {
+                        Method::from_bytes(method.as_bytes())
+                            .map_err(|_| ConfigError::InvalidMethod(method))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+impl From<&ExposeHeaders> for BinaryExposeHeaders {
+    fn from(value: &ExposeHeaders) -> Self {
+        match value {
+            ExposeHeaders::Any => BinaryExposeHeaders::Any,
+            ExposeHeaders::None => BinaryExposeHeaders::None,
+            ExposeHeaders::SameAsAllowed => BinaryExposeHeaders::SameAsAllowed,
+            ExposeHeaders::List(headers) => {
+                BinaryExposeHeaders::List(sorted(headers.iter().map(HeaderName::as_str)))
+            }
+        }
+    }
+}
+
+impl TryFrom<BinaryExposeHeaders> for ExposeHeaders {
+    type Error = ConfigError;
+
+    fn try_from(value: BinaryExposeHeaders) -> Result<Self, Self::Error> {
+        Ok(match value {
+            BinaryExposeHeaders::Any => ExposeHeaders::Any,
+            BinaryExposeHeaders::None => ExposeHeaders::None,
+            BinaryExposeHeaders::SameAsAllowed => ExposeHeaders::SameAsAllowed,
+            BinaryExposeHeaders::List(names) => ExposeHeaders::List(header_names(names)?),
+        })
+    }
+}
+
+impl TryFrom<&Config> for BinaryConfig {
+    type Error = ConfigError;
+
+    fn try_from(value: &Config) -> Result<Self, Self::Error> {
+        Ok(BinaryConfig {
+            enabled: value.enabled,
+            allow_credentials: value.allow_credentials,
+            allowed_headers: (&value.allowed_headers).into(),
+            allowed_methods: (&value.allowed_methods).into(),
+            allowed_origins: (&value.allowed_origins).try_into()?,
+            denied_origins: value.denied_origins.as_ref().map(patterns),
+            allow_private_network: value.allow_private_network,
+            max_age: value.max_age.into(),
+            max_age_by_origin: value
+                .max_age_by_origin
+                .iter()
+                .map(|entry| Ok(((&entry.origins).try_into()?, entry.max_age)))
+                .collect::<Result<_, ConfigError>>()?,
+            expose_headers: (&value.expose_headers).into(),
+            vary: sorted(value.vary.0.iter().map(HeaderName::as_str)),
+        })
+    }
+}
+
+impl TryFrom<BinaryConfig> for Config {
+    type Error = ConfigError;
+
+    fn try_from(value: BinaryConfig) -> Result<Self, Self::Error> {
+        Ok(Config {
+            enabled: value.enabled,
+            version: CONFIG_VERSION,
+            allow_credentials: value.allow_credentials,
+            allowed_headers: value.allowed_headers.try_into()?,
+            allowed_methods: value.allowed_methods.try_into()?,
+            allowed_origins: value.allowed_origins.try_into()?,
+            denied_origins: value.denied_origins.map(regex_set).transpose()?,
+            allow_private_network: value.allow_private_network,
+            max_age: value.max_age.into(),
+            max_age_by_origin: value
+                .max_age_by_origin
+                .into_iter()
+                .map(|(origins, max_age)| {
+                    Ok(OriginMaxAge {
+                        origins: origins.try_into()?,
+                        max_age,
+                    })
+                })
+                .collect::<Result<_, ConfigError>>()?,
+            expose_headers: value.expose_headers.try_into()?,
+            vary: Vary(header_names(value.vary)?),
+            on_origin_decision: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OriginPredicate;
+
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::MirrorList(HashSet::from([
+                http::header::AUTHORIZATION,
+                http::header::CONTENT_TYPE,
+            ])),
+            allowed_methods: AllowedMethods::list([Method::GET, Method::POST]),
+            allowed_origins: AllowedOrigins::Wildcard(HashSet::from([
+                "https://*.example.com".to_string()
+            ])),
+            denied_origins: Some(
+                SerdeRegexSet::new(RegexSet::new([r"https://evil\.example\.com"]).unwrap())
+                    .unwrap(),
+            ),
+            max_age: MaxAge::Seconds(Duration::from_secs(600)),
+            max_age_by_origin: vec![OriginMaxAge {
+                origins: AllowedOrigins::exact("https://app.example.com"),
+                max_age: Duration::from_secs(60),
+            }],
+            expose_headers: ExposeHeaders::list([http::header::ETAG]),
+            vary: Vary(HashSet::from([http::header::ORIGIN])),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_bincode_roundtrip() {
+        let config = config();
+        // the untagged config-file representation can't be read back
+        let bytes = bincode::serialize(&config).unwrap();
+        assert!(bincode::deserialize::<Config>(&bytes).is_err());
+
+        let bytes = bincode::serialize(&BinaryConfig::try_from(&config).unwrap()).unwrap();
+        let binary: BinaryConfig = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(Config::try_from(binary).unwrap(), config);
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        let mut binary = BinaryConfig::try_from(&config()).unwrap();
+        binary.allowed_origins = BinaryOrigins::Exact(vec!["https://example.com/".to_string()]);
+        assert!(matches!(
+            Config::try_from(binary.clone()),
+            Err(ConfigError::InvalidOrigin(_))
+        ));
+        binary.allowed_origins = BinaryOrigins::List(vec!["(".to_string()]);
+        assert!(matches!(
+            Config::try_from(binary),
+            Err(ConfigError::InvalidRegex(_))
+        ));
+
+        let config = Config {
+            allowed_origins: AllowedOrigins::Dynamic(OriginPredicate::new(|_, _| true)),
+            ..Config::default()
+        };
+        assert!(matches!(
+            BinaryConfig::try_from(&config),
+            Err(ConfigError::DynamicOrigins)
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+use std::{collections::HashSet, time::Duration};
+
+use http::{HeaderName, Method};
+use regex::RegexSet;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders, MaxAge,
+    OriginDecisionHook, OriginMaxAge, SerdeRegexSet, Vary,
+};
+
+/// Builds a [`Config`] without going through serde.
+///
+/// Anything not set explicitly is left as restrictive as possible: no
+/// origins, headers or methods are allowed, nothing is exposed and
+/// credentials are disabled.
+///
+/// [`ConfigBuilder::build`] fails if any pattern passed to
+/// [`ConfigBuilder::allow_origin_regex`] or
+/// [`ConfigBuilder::deny_origin_regex`] doesn't compile, naming all of them.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct ConfigBuilder {
+    config: Config,
+    invalid_patterns: Vec<(String, regex::Error)>,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            // unlike `Config::default()`, not even `GET` and `HEAD`
+            config: Config {
+                allowed_methods: AllowedMethods::List(HashSet::new()),
+                ..Config::default()
+            },
+            invalid_patterns: Vec::new(),
+        }
+    }
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`Config::enabled`]
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.config.enabled = enabled;
+        self
+    }
+
+    pub fn allow_credentials(mut self, allow_credentials: bool) -> Self {
+        self.config.allow_credentials = allow_credentials;
+        self
+    }
+
+    pub fn allow_origin(mut self, origins: AllowedOrigins) -> Self {
+        self.config.allowed_origins = origins;
+        self
+    }
+
+    /// Add a regex to the allowed origins, replacing a policy that isn't a
+    /// regex [`List`](AllowedOrigins::List). An invalid pattern is reported
+    /// by [`ConfigBuilder::build`].
+    pub fn allow_origin_regex(mut self, pattern: impl Into<String>) -> Self {
+        let patterns = match &self.config.allowed_origins {
+            AllowedOrigins::List(patterns) => Some(patterns),
+            _ => None,
+        };
+        if let Some(patterns) = add_pattern(&mut self.invalid_patterns, patterns, pattern.into()) {
+            self.config.allowed_origins = AllowedOrigins::List(patterns);
+        }
+        self
+    }
+
+    pub fn deny_origins(mut self, origins: SerdeRegexSet) -> Self {
+        self.config.denied_origins = Some(origins);
+        self
+    }
+
+    /// Add a regex to the denied origins. An invalid pattern is reported by
+    /// [`ConfigBuilder::build`].
+    pub fn deny_origin_regex(mut self, pattern: impl Into<String>) -> Self {
+        let patterns = self.config.denied_origins.as_ref();
+        if let Some(patterns) = add_pattern(&mut self.invalid_patterns, patterns, pattern.into()) {
+            self.config.denied_origins = Some(patterns);
+        }
+        self
+    }
+
+    pub fn allow_headers(mut self, headers: AllowedHeaders) -> Self {
+        self.config.allowed_headers = headers;
+        self
+    }
+
+    /// Add a header to the allowed list, replacing `Any`/`Mirror` if set
+    pub fn allow_header(mut self, header: HeaderName) -> Self {
+        match &mut self.config.allowed_headers {
+            AllowedHeaders::MirrorList(headers) | AllowedHeaders::List(headers) => {
+                headers.insert(header);
+            }
+            allowed_headers => *allowed_headers = AllowedHeaders::List(HashSet::from([header])),
+        }
+        self
+    }
+
+    pub fn allow_methods(mut self, methods: AllowedMethods) -> Self {
+        self.config.allowed_methods = methods;
+        self
+    }
+
+    /// Add a method to the allowed list, replacing `Any`/`Mirror` if set
+    pub fn allow_method(mut self, method: Method) -> Self {
+        match &mut self.config.allowed_methods {
+            AllowedMethods::List(methods) => {
+                methods.insert(method);
+            }
+            allowed_methods => *allowed_methods = AllowedMethods::List(HashSet::from([method])),
+        }
+        self
+    }
+
+    pub fn allow_private_network(mut self, allow_private_network: bool) -> Self {
+        self.config.allow_private_network = allow_private_network;
+        self
+    }
+
+    /// Set the max age to a [`Duration`] or a [`MaxAge`]
+    pub fn max_age(mut self, max_age: impl Into<MaxAge>) -> Self {
+        self.config.max_age = max_age.into();
+        self
+    }
+
+    /// Use a different max age for preflights from `origins`, see
+    /// [`Config::max_age_by_origin`]
+    pub fn max_age_for(mut self, origins: AllowedOrigins, max_age: Duration) -> Self {
+        self.config
+            .max_age_by_origin
+            .push(OriginMaxAge { origins, max_age });
+        self
+    }
+
+    pub fn expose_headers(mut self, headers: ExposeHeaders) -> Self {
+        self.config.expose_headers = headers;
+        self
+    }
+
+    /// Add a header to the exposed list, replacing `Any` if set
+    pub fn expose_header(mut self, header: HeaderName) -> Self {
+        match &mut self.config.expose_headers {
+            ExposeHeaders::List(headers) => {
+                headers.insert(header);
+            }
+            expose_headers => *expose_headers = ExposeHeaders::List(HashSet::from([header])),
+        }
+        self
+    }
+
+    pub fn vary(mut self, vary: Vary) -> Self {
+        self.config.vary = vary;
+        self
+    }
+
+    /// Call `hook` with every origin the layer checks, see
+    /// [`OriginDecisionHook`]
+    pub fn on_origin_decision(mut self, hook: impl Fn(&str, bool) + Send + Sync + 'static) -> Self {
+        self.config.on_origin_decision = Some(OriginDecisionHook::new(hook));
+        self
+    }
+
+    /// Finish the config, failing if any origin pattern was invalid
+    pub fn build(self) -> Result<Config, ConfigError> {
+        if !self.invalid_patterns.is_empty() {
+            return Err(ConfigError::InvalidRegexes(self.invalid_patterns));
+        }
+        Ok(self.config)
+    }
+}
+
+/// `patterns` with `pattern` added, or `None` after recording in `invalid`
+/// why it doesn't compile
+fn add_pattern(
+    invalid: &mut Vec<(String, regex::Error)>,
+    patterns: Option<&SerdeRegexSet>,
+    pattern: String,
+) -> Option<SerdeRegexSet> {
+    let existing = patterns.map_or(&[][..], |patterns| patterns.patterns());
+    match RegexSet::new(existing.iter().chain([&pattern])).and_then(SerdeRegexSet::new) {
+        Ok(patterns) => Some(patterns),
+        Err(error) => {
+            invalid.push((pattern, error));
+            None
+        }
+    }
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_incremental_lists() {
+        let config = Config::builder()
+            .allow_credentials(true)
+            .allow_headers(AllowedHeaders::Mirror)
+            .allow_header(http::header::AUTHORIZATION)
+            .allow_header(http::header::CONTENT_TYPE)
+            .allow_method(Method::GET)
+            .allow_method(Method::POST)
+            .max_age(Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        assert!(config.allow_credentials);
+        assert_eq!(
+            config.allowed_headers,
+            AllowedHeaders::List(HashSet::from([
+                http::header::AUTHORIZATION,
+                http::header::CONTENT_TYPE
+            ]))
+        );
+        assert_eq!(
+            config.allowed_methods,
+            AllowedMethods::List(HashSet::from([Method::GET, Method::POST]))
+        );
+        assert_eq!(config.max_age, MaxAge::Seconds(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_defaults_are_restrictive() {
+        let config = ConfigBuilder::new().build().unwrap();
+        assert!(!config.allow_credentials);
+        assert_eq!(config.allowed_headers, AllowedHeaders::List(HashSet::new()));
+        assert_eq!(config.allowed_methods, AllowedMethods::List(HashSet::new()));
+        assert!(
+            matches!(&config.allowed_origins, AllowedOrigins::List(origins) if origins.is_empty())
+        );
+        assert_eq!(config.expose_headers, ExposeHeaders::List(HashSet::new()));
+    }
+
+    #[test]
+    fn test_origin_regexes() {
+        let config = Config::builder()
+            .allow_origin(AllowedOrigins::Any)
+            .allow_origin_regex(r"https://a\.example")
+            .allow_origin_regex(r"https://b\.example")
+            .deny_origin_regex(r"https://evil\.a\.example")
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.allowed_origins,
+            AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://a\.example", r"https://b\.example"]).unwrap()
+                )
+                .unwrap()
+            )
+        );
+        assert!(config.denied_origins.is_some());
+
+        let error = Config::builder()
+            .allow_origin_regex(r"https://good\.example")
+            .allow_origin_regex(r"https://(bad\.example")
+            .deny_origin_regex("[worse")
+            .build()
+            .unwrap_err();
+        let ConfigError::InvalidRegexes(errors) = &error else {
+            panic!("unexpected error {error:?}");
+        };
+        let patterns = errors
+            .iter()
+            .map(|(pattern, _)| pattern.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(patterns, [r"https://(bad\.example", "[worse"]);
+        assert_eq!(
+            error.to_string(),
+            r"invalid origin patterns `https://(bad\.example`, `[worse`"
+        );
+    }
+}
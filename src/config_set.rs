@@ -0,0 +1,191 @@
+//! Different configs for different parts of a service
+
+use std::collections::BTreeMap;
+
+use tower_http::cors::CorsLayer;
+
+use crate::{Config, ConfigError};
+
+/// A [`Config`] per path prefix, e.g. strict rules for `/api` and permissive
+/// ones for `/public`.
+///
+/// Every config is turned into a layer up front, so an invalid one is
+/// reported when the set is created rather than when a request comes in.
+/// Prefixes match whole path segments: `/api` covers `/api` and `/api/users`
+/// but not `/apis`. When several prefixes match, the longest wins, and `/`
+/// can be used as a fallback. In config files a set is written as a map from
+/// prefix to config.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "BTreeMap<String, Config>",
+        into = "BTreeMap<String, Config>"
+    )
+)]
+pub struct ConfigSet {
+    configs: BTreeMap<String, Config>,
+    /// Sorted longest prefix first
+    layers: Vec<(String, CorsLayer)>,
+}
+
+impl ConfigSet {
+    /// Build a layer for every prefix, failing on the first invalid config
+    pub fn new(
+        configs: impl IntoIterator<Item = (impl Into<String>, Config)>,
+    ) -> Result<ConfigSet, ConfigError> {
+        let configs: BTreeMap<String, Config> = configs
+            .into_iter()
+            .map(|(prefix, config)| (prefix.into(), config))
+            .collect();
+        let mut layers = configs
+            .iter()
+            .map(|(prefix, config)| Ok((prefix.clone(), CorsLayer::try_from(config.clone())?)))
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+        layers.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(ConfigSet { configs, layers })
+    }
+
+    /// The configs in the set, keyed by prefix
+    pub fn configs(&self) -> &BTreeMap<String, Config> {
+        &self.configs
+    }
+
+    /// The config for the longest prefix matching `path`
+    pub fn config_for(&self, path: &str) -> Option<&Config> {
+        self.prefix_for(path).map(|prefix| &self.configs[prefix])
+    }
+
+    /// The layer for the longest prefix matching `path`
+    pub fn layer_for(&self, path: &str) -> Option<CorsLayer> {
+        let prefix = self.prefix_for(path)?;
+        self.layers
+            .iter()
+            .find(|(candidate, _)| candidate == prefix)
+            .map(|(_, layer)| layer.clone())
+    }
+
+    fn prefix_for(&self, path: &str) -> Option<&str> {
+        self.layers
+            .iter()
+            .map(|(prefix, _)| prefix.as_str())
+            .find(|prefix| matches_prefix(prefix, path))
+    }
+}
+
+/// Whether `prefix` covers `path`, comparing whole segments
+fn matches_prefix(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+impl TryFrom<BTreeMap<String, Config>> for ConfigSet {
+    type Error = ConfigError;
+
+    fn try_from(configs: BTreeMap<String, Config>) -> Result<Self, Self::Error> {
+        ConfigSet::new(configs)
+    }
+}
+
+impl From<ConfigSet> for BTreeMap<String, Config> {
+    fn from(set: ConfigSet) -> Self {
+        set.configs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{cors_headers, request_from},
+        AllowedOrigins,
+    };
+
+    fn config_set() -> ConfigSet {
+        ConfigSet::new([
+            ("/", Config::deny_all()),
+            ("/public", Config::permissive()),
+            (
+                "/api",
+                Config {
+                    allowed_origins: AllowedOrigins::exact("https://app.example"),
+                    ..Config::permissive()
+                },
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_longest_segment_prefix_wins() {
+        let set = config_set();
+        assert_eq!(
+            set.config_for("/public/logo.png"),
+            Some(&Config::permissive())
+        );
+        assert_eq!(set.config_for("/public"), Some(&Config::permissive()));
+        assert_eq!(set.config_for("/publications"), Some(&Config::deny_all()));
+        assert_eq!(set.config_for("/"), Some(&Config::deny_all()));
+
+        let allow_origin = |path, origin| {
+            let layer = set.layer_for(path).unwrap();
+            cors_headers(layer, request_from(origin))
+                .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .cloned()
+        };
+        assert_eq!(allow_origin("/public/a", "https://x.example").unwrap(), "*");
+        assert_eq!(
+            allow_origin("/api/users", "https://app.example").unwrap(),
+            "https://app.example"
+        );
+        assert_eq!(allow_origin("/api/users", "https://x.example"), None);
+        assert_eq!(allow_origin("/other", "https://app.example"), None);
+    }
+
+    #[test]
+    fn test_no_match_and_invalid_configs() {
+        let set = ConfigSet::new([("/api", Config::permissive())]).unwrap();
+        assert!(set.layer_for("/").is_none());
+
+        let invalid = Config {
+            allow_credentials: true,
+            ..Config::permissive()
+        };
+        assert!(matches!(
+            ConfigSet::new([("/api", invalid)]),
+            Err(ConfigError::CredentialsWithWildcard { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize() {
+        let set: ConfigSet = serde_yaml::from_str(
+            "
+            /public:
+              allowed-origins: any
+              allowed-headers: any
+              allowed-methods: any
+              expose-headers: any
+            ",
+        )
+        .unwrap();
+        assert_eq!(set.config_for("/public/a"), Some(&Config::permissive()));
+
+        let error = serde_yaml::from_str::<ConfigSet>(
+            "
+            /api:
+              allow-credentials: true
+              allowed-origins: any
+              allowed-headers: []
+              allowed-methods: []
+              expose-headers: []
+            ",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("allow-credentials"), "{error}");
+    }
+}
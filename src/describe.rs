@@ -0,0 +1,450 @@
+//! Human-readable summaries of a [`Config`] and its parts
+
+use std::{collections::HashSet, fmt, time::Duration};
+
+use http::HeaderName;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ExposeHeaders, HeaderCap, MaxAge,
+    SerdeRegexSet,
+};
+
+impl Config {
+    /// Summarize the config for humans, e.g. in a support ticket:
+    /// `Origins: any; Methods: GET, POST; Credentials: disabled; ...`.
+    ///
+    /// Unlike `Debug`, regex patterns are spelled out and every list is
+    /// sorted. The exact wording isn't stable, so don't parse it.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "Origins: {}",
+            describe_origins(&self.allowed_origins)
+        )];
+        if !self.enabled {
+            parts.insert(0, "CORS: disabled".to_string());
+        }
+        if let Some(denied) = &self.denied_origins {
+            parts.push(format!("Denied origins: {}", describe_patterns(denied)));
+        }
+        parts.push(format!(
+            "Methods: {}",
+            match &self.allowed_methods {
+                AllowedMethods::Any => "any".to_string(),
+                AllowedMethods::Mirror => "mirror".to_string(),
+                AllowedMethods::List(methods) => {
+                    describe_list(methods.iter().map(|method| method.as_str()))
+                }
+            }
+        ));
+        parts.push(format!(
+            "Headers: {}",
+            match &self.allowed_headers {
+                AllowedHeaders::Any => "any".to_string(),
+                AllowedHeaders::Mirror => "mirror".to_string(),
+                AllowedHeaders::MirrorCapped(HeaderCap {
+                    max_headers,
+                    max_bytes,
+                }) => format!("mirror of up to {max_headers} headers and {max_bytes} bytes"),
+                AllowedHeaders::MirrorList(headers) => {
+                    format!("mirror of {}", describe_headers(headers))
+                }
+                AllowedHeaders::Regex(patterns) => {
+                    format!("mirror matching {}", describe_patterns(patterns))
+                }
+                AllowedHeaders::List(headers) => describe_headers(headers),
+            }
+        ));
+        parts.push(format!(
+            "Expose headers: {}",
+            match &self.expose_headers {
+                ExposeHeaders::Any => "any".to_string(),
+                ExposeHeaders::None => "none".to_string(),
+                ExposeHeaders::SameAsAllowed => "same as allowed".to_string(),
+                ExposeHeaders::List(headers) => describe_headers(headers),
+            }
+        ));
+        parts.push(format!("Credentials: {}", enabled(self.allow_credentials)));
+        parts.push(format!(
+            "Private network: {}",
+            enabled(self.allow_private_network)
+        ));
+        parts.push(format!("Max-Age: {}", describe_max_age(self.max_age)));
+        for group in &self.max_age_by_origin {
+            parts.push(format!(
+                "Max-Age for {}: {}",
+                describe_origins(&group.origins),
+                describe_duration(group.max_age)
+            ));
+        }
+        parts.push(format!("Vary: {}", describe_headers(&self.vary.0)));
+        if self.on_origin_decision.is_some() {
+            parts.push("Origin decision hook: set".to_string());
+        }
+        parts.join("; ")
+    }
+}
+
+impl Config {
+    /// The CORS response headers this config can cause the layer to send, in
+    /// a fixed order, e.g. for documenting an API.
+    ///
+    /// Empty lists and disabled flags send nothing, so their headers are left
+    /// out. Some headers only appear on preflights or only for allowed
+    /// origins; this lists everything that can appear.
+    pub fn response_headers(&self) -> Vec<HeaderName> {
+        use http::header;
+
+        let origins = match &self.allowed_origins {
+            AllowedOrigins::Exact(origins) | AllowedOrigins::Wildcard(origins) => {
+                !origins.is_empty()
+            }
+            AllowedOrigins::List(patterns) => !patterns.is_empty(),
+            AllowedOrigins::Any
+            | AllowedOrigins::Mirror
+            | AllowedOrigins::LocalhostAny
+            | AllowedOrigins::Dynamic(_)
+            | AllowedOrigins::Shared(_) => true,
+        };
+        let methods = self
+            .allowed_methods
+            .as_sorted_vec()
+            .is_none_or(|methods| !methods.is_empty());
+        let headers = self
+            .allowed_headers
+            .as_sorted_vec()
+            .is_none_or(|headers| !headers.is_empty());
+        let expose = self
+            .resolved_expose_headers()
+            .as_sorted_vec()
+            .is_none_or(|headers| !headers.is_empty());
+        [
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, origins),
+            (header::ACCESS_CONTROL_ALLOW_METHODS, methods),
+            (header::ACCESS_CONTROL_ALLOW_HEADERS, headers),
+            (
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                self.allow_credentials,
+            ),
+            (
+                header::ACCESS_CONTROL_MAX_AGE,
+                !self.max_age.is_disabled() || !self.max_age_by_origin.is_empty(),
+            ),
+            (header::ACCESS_CONTROL_EXPOSE_HEADERS, expose),
+            (
+                HeaderName::from_static("access-control-allow-private-network"),
+                self.allow_private_network,
+            ),
+            (header::VARY, !self.vary.is_empty()),
+        ]
+        .into_iter()
+        .filter(|(_, sent)| *sent)
+        .map(|(header, _)| header)
+        .collect()
+    }
+}
+
+fn describe_origins(origins: &AllowedOrigins) -> String {
+    match origins {
+        AllowedOrigins::Any => "any".to_string(),
+        AllowedOrigins::Mirror => "mirror".to_string(),
+        AllowedOrigins::LocalhostAny => "any localhost".to_string(),
+        AllowedOrigins::Dynamic(_) => "custom predicate".to_string(),
+        AllowedOrigins::Shared(origins) => {
+            format!("shared {}", describe_patterns(&origins.patterns()))
+        }
+        AllowedOrigins::Exact(origins) => describe_list(origins.iter().map(String::as_str)),
+        AllowedOrigins::Wildcard(patterns) => format!(
+            "wildcard {}",
+            describe_list(patterns.iter().map(String::as_str))
+        ),
+        AllowedOrigins::List(patterns) => describe_patterns(patterns),
+    }
+}
+
+fn describe_patterns(patterns: &SerdeRegexSet) -> String {
+    if patterns.is_empty() {
+        return "none".to_string();
+    }
+    format!(
+        "regex {}",
+        describe_list(patterns.patterns().iter().map(String::as_str))
+    )
+}
+
+/// Prints the config-file form, e.g. `any`, `exact: [https://a.example]` or
+/// a sorted regex list. [`AllowedOrigins::Dynamic`] prints as `dynamic` and
+/// [`AllowedOrigins::Shared`] as `shared`.
+impl fmt::Display for AllowedOrigins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowedOrigins::Any => f.write_str("any"),
+            AllowedOrigins::Mirror => f.write_str("mirror"),
+            AllowedOrigins::LocalhostAny => f.write_str("any-localhost"),
+            AllowedOrigins::Dynamic(_) => f.write_str("dynamic"),
+            AllowedOrigins::Shared(_) => f.write_str("shared"),
+            AllowedOrigins::Exact(origins) => {
+                write!(
+                    f,
+                    "exact: {}",
+                    bracketed(origins.iter().map(String::as_str))
+                )
+            }
+            AllowedOrigins::Wildcard(patterns) => write!(
+                f,
+                "wildcard: {}",
+                bracketed(patterns.iter().map(String::as_str))
+            ),
+            AllowedOrigins::List(patterns) => {
+                f.write_str(&bracketed(patterns.patterns().iter().map(String::as_str)))
+            }
+        }
+    }
+}
+
+/// Prints the config-file form, e.g. `mirror` or `[content-type, x-trace]`
+impl fmt::Display for AllowedHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowedHeaders::Any => f.write_str("any"),
+            AllowedHeaders::Mirror => f.write_str("mirror"),
+            AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers,
+                max_bytes,
+            }) => write!(
+                f,
+                "mirror-capped: {{ max-headers: {max_headers}, max-bytes: {max_bytes} }}"
+            ),
+            AllowedHeaders::MirrorList(headers) => write!(
+                f,
+                "mirror-list: {}",
+                bracketed(headers.iter().map(HeaderName::as_str))
+            ),
+            AllowedHeaders::Regex(patterns) => write!(
+                f,
+                "regex: {}",
+                bracketed(patterns.patterns().iter().map(String::as_str))
+            ),
+            AllowedHeaders::List(headers) => {
+                f.write_str(&bracketed(headers.iter().map(HeaderName::as_str)))
+            }
+        }
+    }
+}
+
+/// Prints the config-file form, e.g. `any` or `[GET, POST]`
+impl fmt::Display for AllowedMethods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowedMethods::Any => f.write_str("any"),
+            AllowedMethods::Mirror => f.write_str("mirror"),
+            AllowedMethods::List(methods) => {
+                f.write_str(&bracketed(methods.iter().map(|method| method.as_str())))
+            }
+        }
+    }
+}
+
+/// Prints the config-file form, e.g. `none` or `[etag]`
+impl fmt::Display for ExposeHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExposeHeaders::Any => f.write_str("any"),
+            ExposeHeaders::None => f.write_str("none"),
+            ExposeHeaders::SameAsAllowed => f.write_str("same-as-allowed"),
+            ExposeHeaders::List(headers) => {
+                f.write_str(&bracketed(headers.iter().map(HeaderName::as_str)))
+            }
+        }
+    }
+}
+
+/// Sort `items` and join them as `[a, b]`
+pub(crate) fn bracketed<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    let mut items = items.collect::<Vec<_>>();
+    items.sort_unstable();
+    format!("[{}]", items.join(", "))
+}
+
+fn describe_headers(headers: &HashSet<HeaderName>) -> String {
+    describe_list(headers.iter().map(HeaderName::as_str))
+}
+
+/// Sort and join `items`, or `none` if there aren't any
+fn describe_list<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    let mut items = items.collect::<Vec<_>>();
+    if items.is_empty() {
+        return "none".to_string();
+    }
+    items.sort_unstable();
+    items.join(", ")
+}
+
+fn enabled(flag: bool) -> &'static str {
+    if flag {
+        "enabled"
+    } else {
+        "disabled"
+    }
+}
+
+/// `unset` for a disabled max age, otherwise its config-file form
+pub(crate) fn describe_max_age(max_age: MaxAge) -> String {
+    match max_age {
+        MaxAge::Disabled => "unset".to_string(),
+        max_age => max_age.to_string(),
+    }
+}
+
+/// Prints the config-file form, e.g. `10m`, `disabled` or `browser-max`
+impl fmt::Display for MaxAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaxAge::Disabled => f.write_str("disabled"),
+            MaxAge::Seconds(max_age) => f.write_str(&describe_duration(*max_age)),
+            MaxAge::BrowserMax => f.write_str("browser-max"),
+        }
+    }
+}
+
+/// Format `duration` in the largest whole unit, e.g. `90m` or `1500ms`
+pub(crate) fn describe_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if duration.subsec_nanos() != 0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs != 0 && secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs != 0 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, Method};
+
+    use super::*;
+    use crate::{OriginMaxAge, Vary};
+
+    #[test]
+    fn test_describe() {
+        let config = Config {
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::List(HashSet::from([
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+            ])),
+            allowed_methods: AllowedMethods::List(HashSet::from([Method::POST, Method::GET])),
+            allowed_origins: AllowedOrigins::regexes(&[
+                r"https://b\.example",
+                r"https://a\.example",
+            ])
+            .unwrap(),
+            max_age: MaxAge::Seconds(Duration::from_secs(3600)),
+            max_age_by_origin: vec![OriginMaxAge {
+                origins: AllowedOrigins::exact("https://a.example"),
+                max_age: Duration::from_secs(90),
+            }],
+            expose_headers: ExposeHeaders::None,
+            vary: Vary(HashSet::from([header::ORIGIN])),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.describe(),
+            "Origins: regex https://a\\.example, https://b\\.example; \
+             Methods: GET, POST; \
+             Headers: authorization, content-type; \
+             Expose headers: none; \
+             Credentials: enabled; \
+             Private network: disabled; \
+             Max-Age: 1h; \
+             Max-Age for https://a.example: 90s; \
+             Vary: origin"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(AllowedOrigins::Any.to_string(), "any");
+        assert_eq!(AllowedOrigins::LocalhostAny.to_string(), "any-localhost");
+        assert_eq!(
+            AllowedOrigins::Exact(HashSet::from([
+                "https://b.example".to_string(),
+                "https://a.example".to_string()
+            ]))
+            .to_string(),
+            "exact: [https://a.example, https://b.example]"
+        );
+        assert_eq!(
+            AllowedOrigins::regexes(&[r"https://.*\.example"])
+                .unwrap()
+                .to_string(),
+            r"[https://.*\.example]"
+        );
+        assert_eq!(
+            AllowedMethods::list([Method::POST, Method::GET]).to_string(),
+            "[GET, POST]"
+        );
+        assert_eq!(AllowedMethods::none().to_string(), "[]");
+        assert_eq!(AllowedHeaders::Mirror.to_string(), "mirror");
+        assert_eq!(
+            AllowedHeaders::MirrorCapped(HeaderCap::default()).to_string(),
+            "mirror-capped: { max-headers: 32, max-bytes: 1024 }"
+        );
+        assert_eq!(
+            AllowedHeaders::MirrorList(HashSet::from([header::AUTHORIZATION])).to_string(),
+            "mirror-list: [authorization]"
+        );
+        assert_eq!(ExposeHeaders::none().to_string(), "none");
+        assert_eq!(
+            ExposeHeaders::list([header::ETAG, header::CONTENT_LENGTH]).to_string(),
+            "[content-length, etag]"
+        );
+    }
+
+    #[test]
+    fn test_response_headers() {
+        assert_eq!(
+            Config::permissive().response_headers(),
+            [
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            ]
+        );
+        let config = Config {
+            allow_credentials: true,
+            allowed_origins: AllowedOrigins::exact("https://a.example"),
+            allow_private_network: true,
+            max_age: MaxAge::Seconds(Duration::from_secs(60)),
+            vary: Vary(HashSet::from([header::ORIGIN])),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.response_headers(),
+            [
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderName::from_static("access-control-allow-private-network"),
+                header::VARY,
+            ]
+        );
+        assert!(Config::deny_all().response_headers().is_empty());
+    }
+
+    #[test]
+    fn test_describe_presets() {
+        assert_eq!(
+            Config::permissive().describe(),
+            "Origins: any; Methods: any; Headers: any; Expose headers: any; \
+             Credentials: disabled; Private network: disabled; Max-Age: unset; Vary: none"
+        );
+        assert!(Config::deny_all()
+            .describe()
+            .starts_with("Origins: none; Methods: none;"));
+    }
+}
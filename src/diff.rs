@@ -0,0 +1,203 @@
+//! Field-by-field comparison of two configs, e.g. for audit logs
+
+use std::fmt::Display;
+
+use crate::{
+    describe::{bracketed, describe_duration, describe_max_age},
+    Config, OriginMaxAge,
+};
+
+/// One field that differs between two configs, with both values in their
+/// config-file form
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct FieldDiff {
+    /// The field's name in config files, e.g. `allowed-origins`
+    pub field: &'static str,
+    /// The value in `self`
+    pub old: String,
+    /// The value in `other`
+    pub new: String,
+}
+
+impl Config {
+    /// The fields whose values differ in `other`, in declaration order.
+    ///
+    /// Lists are compared and printed sorted, so reordering entries isn't a
+    /// change. `on_origin_decision` isn't part of the config file and is
+    /// ignored, and so is `version`, which doesn't change the policy.
+    pub fn diff(&self, other: &Config) -> Vec<FieldDiff> {
+        let mut diffs = Vec::new();
+        let mut compare = |field, old: &dyn Display, new: &dyn Display, changed: bool| {
+            if changed {
+                diffs.push(FieldDiff {
+                    field,
+                    old: old.to_string(),
+                    new: new.to_string(),
+                });
+            }
+        };
+        compare(
+            "enabled",
+            &self.enabled,
+            &other.enabled,
+            self.enabled != other.enabled,
+        );
+        compare(
+            "allow-credentials",
+            &self.allow_credentials,
+            &other.allow_credentials,
+            self.allow_credentials != other.allow_credentials,
+        );
+        compare(
+            "allowed-headers",
+            &self.allowed_headers,
+            &other.allowed_headers,
+            self.allowed_headers != other.allowed_headers,
+        );
+        compare(
+            "allowed-methods",
+            &self.allowed_methods,
+            &other.allowed_methods,
+            self.allowed_methods != other.allowed_methods,
+        );
+        compare(
+            "allowed-origins",
+            &self.allowed_origins,
+            &other.allowed_origins,
+            self.allowed_origins != other.allowed_origins,
+        );
+        compare(
+            "denied-origins",
+            &denied_origins(self),
+            &denied_origins(other),
+            self.denied_origins != other.denied_origins,
+        );
+        compare(
+            "allow-private-network",
+            &self.allow_private_network,
+            &other.allow_private_network,
+            self.allow_private_network != other.allow_private_network,
+        );
+        compare(
+            "max-age",
+            &max_age(self),
+            &max_age(other),
+            self.max_age != other.max_age,
+        );
+        compare(
+            "max-age-by-origin",
+            &max_age_by_origin(&self.max_age_by_origin),
+            &max_age_by_origin(&other.max_age_by_origin),
+            self.max_age_by_origin != other.max_age_by_origin,
+        );
+        compare(
+            "expose-headers",
+            &self.expose_headers,
+            &other.expose_headers,
+            self.expose_headers != other.expose_headers,
+        );
+        compare(
+            "vary",
+            &bracketed(self.vary.0.iter().map(|header| header.as_str())),
+            &bracketed(other.vary.0.iter().map(|header| header.as_str())),
+            self.vary != other.vary,
+        );
+        diffs
+    }
+}
+
+fn denied_origins(config: &Config) -> String {
+    config
+        .denied_origins
+        .as_ref()
+        .map_or("unset".to_string(), |denied| {
+            bracketed(denied.patterns().iter().map(String::as_str))
+        })
+}
+
+fn max_age(config: &Config) -> String {
+    describe_max_age(config.max_age)
+}
+
+/// Groups keep their order, since the first match wins
+fn max_age_by_origin(groups: &[OriginMaxAge]) -> String {
+    let groups = groups
+        .iter()
+        .map(|group| format!("{}: {}", group.origins, describe_duration(group.max_age)))
+        .collect::<Vec<_>>();
+    format!("[{}]", groups.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use http::{header, Method};
+
+    use super::*;
+    use crate::{AllowedMethods, AllowedOrigins, MaxAge};
+
+    #[test]
+    fn test_diff() {
+        let old = Config {
+            allowed_origins: AllowedOrigins::exact("https://a.example"),
+            allowed_methods: AllowedMethods::list([Method::GET, Method::POST]),
+            ..Config::default()
+        };
+        assert!(old.diff(&old.clone()).is_empty());
+
+        let new = Config {
+            allowed_origins: AllowedOrigins::Exact(HashSet::from([
+                "https://b.example".to_string(),
+                "https://a.example".to_string(),
+            ])),
+            allowed_methods: AllowedMethods::list([Method::POST, Method::GET]),
+            max_age: MaxAge::Seconds(Duration::from_secs(600)),
+            ..old.clone()
+        };
+        assert_eq!(
+            old.diff(&new),
+            [
+                FieldDiff {
+                    field: "allowed-origins",
+                    old: "exact: [https://a.example]".to_string(),
+                    new: "exact: [https://a.example, https://b.example]".to_string(),
+                },
+                FieldDiff {
+                    field: "max-age",
+                    old: "unset".to_string(),
+                    new: "10m".to_string(),
+                },
+            ]
+        );
+
+        let new = Config {
+            expose_headers: crate::ExposeHeaders::list([header::ETAG]),
+            ..old.clone()
+        };
+        assert_eq!(
+            old.diff(&new),
+            [FieldDiff {
+                field: "expose-headers",
+                old: "[]".to_string(),
+                new: "[etag]".to_string(),
+            }]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_field_diff_serializes() {
+        let diff = FieldDiff {
+            field: "allow-credentials",
+            old: "false".to_string(),
+            new: "true".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&diff).unwrap(),
+            r#"{"field":"allow-credentials","old":"false","new":"true"}"#
+        );
+    }
+}
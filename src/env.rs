@@ -0,0 +1,279 @@
+//! Loading a [`Config`] from environment variables
+
+use std::collections::HashSet;
+
+use http::HeaderName;
+use regex::RegexSet;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigBuilder, ConfigError,
+    ExposeHeaders, MaxAge, Origin, SerdeRegexSet, Vary,
+};
+
+impl Config {
+    /// Read a config from environment variables named `{prefix}_{FIELD}`.
+    ///
+    /// With a prefix of `CORS` the following variables are read, all of them
+    /// optional. Anything unset keeps the restrictive default of
+    /// [`ConfigBuilder`].
+    ///
+    /// | Variable                     | Format                                                                      |
+    /// |------------------------------|-----------------------------------------------------------------------------|
+    /// | `CORS_ENABLED`               | `true` or `false`                                                           |
+    /// | `CORS_ALLOW_CREDENTIALS`     | `true` or `false`                                                           |
+    /// | `CORS_ALLOWED_ORIGINS`       | `any`, `mirror`, `any-localhost` or a list                                  |
+    /// | `CORS_ALLOWED_HEADERS`       | `any`, `mirror` or a comma-separated list, `regex:` for patterns            |
+    /// | `CORS_ALLOWED_METHODS`       | `any`, `mirror` or a comma-separated list                                   |
+    /// | `CORS_ALLOW_PRIVATE_NETWORK` | `true` or `false`                                                           |
+    /// | `CORS_MAX_AGE`               | a [humantime](humantime::parse_duration) value, `disabled` or `browser-max` |
+    /// | `CORS_EXPOSE_HEADERS`        | `any`, `none`, `same-as-allowed` or a list                                  |
+    /// | `CORS_VARY`                  | a comma-separated list                                                      |
+    ///
+    /// Origins are matched exactly ([`AllowedOrigins::Exact`]) unless the list
+    /// starts with `regex:` ([`AllowedOrigins::List`]) or `wildcard:`
+    /// ([`AllowedOrigins::Wildcard`]), e.g.
+    /// `CORS_ALLOWED_ORIGINS=regex:https://.*\.example\.com`. Since entries are
+    /// split on commas, regex patterns can't contain a literal `,`. Methods are
+    /// case-insensitive, and `*` or `all` stands for
+    /// [`STANDARD_METHODS`](crate::STANDARD_METHODS).
+    pub fn from_env(prefix: &str) -> Result<Config, ConfigError> {
+        Config::from_lookup(prefix, |name| std::env::var_os(name))
+    }
+
+    fn from_lookup(
+        prefix: &str,
+        lookup: impl Fn(&str) -> Option<std::ffi::OsString>,
+    ) -> Result<Config, ConfigError> {
+        let var = |field: &str| -> Result<Option<(String, String)>, ConfigError> {
+            let name = format!("{prefix}_{field}");
+            match lookup(&name) {
+                None => Ok(None),
+                Some(value) => match value.into_string() {
+                    Ok(value) => Ok(Some((name, value.trim().to_string()))),
+                    Err(value) => Err(ConfigError::InvalidEnvVar {
+                        name,
+                        value: value.to_string_lossy().into_owned(),
+                    }),
+                },
+            }
+        };
+
+        let mut config = ConfigBuilder::new().build()?;
+        if let Some((name, value)) = var("ENABLED")? {
+            config.enabled = parse_bool(name, value)?;
+        }
+        if let Some((name, value)) = var("ALLOW_CREDENTIALS")? {
+            config.allow_credentials = parse_bool(name, value)?;
+        }
+        if let Some((_, value)) = var("ALLOWED_ORIGINS")? {
+            config.allowed_origins = parse_origins(&value)?;
+        }
+        if let Some((_, value)) = var("ALLOWED_HEADERS")? {
+            config.allowed_headers = match value.as_str() {
+                "any" => AllowedHeaders::Any,
+                "mirror" => AllowedHeaders::Mirror,
+                _ => match value.strip_prefix("regex:") {
+                    Some(patterns) => {
+                        AllowedHeaders::Regex(SerdeRegexSet::new(RegexSet::new(split(patterns))?)?)
+                    }
+                    None => AllowedHeaders::List(parse_headers(&value)?),
+                },
+            };
+        }
+        if let Some((_, value)) = var("ALLOWED_METHODS")? {
+            config.allowed_methods = match value.as_str() {
+                "any" => AllowedMethods::Any,
+                "mirror" => AllowedMethods::Mirror,
+                _ => AllowedMethods::List(crate::parse_methods(split(&value))?),
+            };
+        }
+        if let Some((name, value)) = var("ALLOW_PRIVATE_NETWORK")? {
+            config.allow_private_network = parse_bool(name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_AGE")? {
+            config.max_age = match value.as_str() {
+                "disabled" => MaxAge::Disabled,
+                "browser-max" => MaxAge::BrowserMax,
+                _ => MaxAge::Seconds(
+                    humantime::parse_duration(&value)
+                        .map_err(|_| ConfigError::InvalidEnvVar { name, value })?,
+                ),
+            };
+        }
+        if let Some((_, value)) = var("EXPOSE_HEADERS")? {
+            config.expose_headers = match value.as_str() {
+                "any" => ExposeHeaders::Any,
+                "none" => ExposeHeaders::None,
+                "same-as-allowed" => ExposeHeaders::SameAsAllowed,
+                _ => ExposeHeaders::List(parse_headers(&value)?),
+            };
+        }
+        if let Some((_, value)) = var("VARY")? {
+            config.vary = Vary(parse_headers(&value)?);
+        }
+        Ok(config)
+    }
+}
+
+fn split(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_bool(name: String, value: String) -> Result<bool, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::InvalidEnvVar { name, value })
+}
+
+fn parse_headers(value: &str) -> Result<HashSet<HeaderName>, ConfigError> {
+    split(value)
+        .map(|header| {
+            HeaderName::try_from(header)
+                .map_err(|_| ConfigError::InvalidHeaderName(header.to_string()))
+        })
+        .collect()
+}
+
+fn parse_origins(value: &str) -> Result<AllowedOrigins, ConfigError> {
+    Ok(match value {
+        "any" => AllowedOrigins::Any,
+        "mirror" => AllowedOrigins::Mirror,
+        "any-localhost" => AllowedOrigins::LocalhostAny,
+        _ => {
+            if let Some(patterns) = value.strip_prefix("regex:") {
+                AllowedOrigins::List(SerdeRegexSet::new(RegexSet::new(split(patterns))?)?)
+            } else if let Some(patterns) = value.strip_prefix("wildcard:") {
+                AllowedOrigins::Wildcard(split(patterns).map(String::from).collect())
+            } else {
+                AllowedOrigins::Exact(
+                    split(value)
+                        .map(|origin| Origin::parse(origin).map(String::from))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, ffi::OsString, time::Duration};
+
+    use http::Method;
+
+    use super::*;
+    use crate::CONFIG_VERSION;
+    use pretty_assertions::assert_eq;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<_, _> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        Config::from_lookup("CORS", |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_from_env() {
+        let config = from_vars(&[
+            ("CORS_ALLOW_CREDENTIALS", "true"),
+            (
+                "CORS_ALLOWED_ORIGINS",
+                "https://a.example, https://b.example",
+            ),
+            ("CORS_ALLOWED_HEADERS", "authorization,content-type"),
+            ("CORS_ALLOWED_METHODS", "GET, POST"),
+            ("CORS_MAX_AGE", "1h"),
+            ("CORS_EXPOSE_HEADERS", "any"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                enabled: true,
+                version: CONFIG_VERSION,
+                allow_credentials: true,
+                allowed_headers: AllowedHeaders::List(HashSet::from([
+                    http::header::AUTHORIZATION,
+                    http::header::CONTENT_TYPE,
+                ])),
+                allowed_methods: AllowedMethods::List(HashSet::from([Method::GET, Method::POST])),
+                allowed_origins: AllowedOrigins::Exact(HashSet::from([
+                    "https://a.example".to_string(),
+                    "https://b.example".to_string(),
+                ])),
+                denied_origins: None,
+                allow_private_network: false,
+                max_age: MaxAge::Seconds(Duration::from_secs(3600)),
+                max_age_by_origin: Vec::new(),
+                expose_headers: ExposeHeaders::Any,
+                vary: Vary::default(),
+                on_origin_decision: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unset_vars_keep_defaults() {
+        assert_eq!(
+            from_vars(&[]).unwrap(),
+            ConfigBuilder::new().build().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_origin_conventions() {
+        let origins = |value| {
+            from_vars(&[("CORS_ALLOWED_ORIGINS", value)])
+                .unwrap()
+                .allowed_origins
+        };
+        assert_eq!(origins("any"), AllowedOrigins::Any);
+        assert_eq!(origins("mirror"), AllowedOrigins::Mirror);
+        assert_eq!(origins("any-localhost"), AllowedOrigins::LocalhostAny);
+        assert_eq!(
+            origins(r"regex:https://a\.example, https://.*\.b\.example"),
+            AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://a\.example", r"https://.*\.b\.example"]).unwrap()
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            origins("wildcard:https://*.example.com"),
+            AllowedOrigins::Wildcard(HashSet::from(["https://*.example.com".to_string()]))
+        );
+        assert_eq!(
+            from_vars(&[("CORS_ALLOWED_HEADERS", "regex:x-myapp-.*")])
+                .unwrap()
+                .allowed_headers,
+            AllowedHeaders::Regex(
+                SerdeRegexSet::new(RegexSet::new(["x-myapp-.*"]).unwrap()).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(matches!(
+            from_vars(&[("CORS_ALLOW_CREDENTIALS", "yes")]),
+            Err(ConfigError::InvalidEnvVar { name, .. }) if name == "CORS_ALLOW_CREDENTIALS"
+        ));
+        assert!(matches!(
+            from_vars(&[("CORS_MAX_AGE", "forever")]),
+            Err(ConfigError::InvalidEnvVar { name, .. }) if name == "CORS_MAX_AGE"
+        ));
+        assert!(matches!(
+            from_vars(&[("CORS_ALLOWED_METHODS", "GET,G(E)T")]),
+            Err(ConfigError::InvalidMethod(method)) if method == "G(E)T"
+        ));
+        assert!(matches!(
+            from_vars(&[("CORS_ALLOWED_ORIGINS", "regex:https://(")]),
+            Err(ConfigError::InvalidRegex(_))
+        ));
+    }
+}
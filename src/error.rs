@@ -0,0 +1,284 @@
+use std::fmt;
+
+use crate::Severity;
+
+/// Errors produced when a [`Config`](crate::Config) can't be loaded or turned
+/// into a working [`CorsLayer`](tower_http::cors::CorsLayer)
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// An origin pattern failed to compile
+    InvalidRegex(regex::Error),
+    /// Origin patterns added with
+    /// [`ConfigBuilder::allow_origin_regex`](crate::ConfigBuilder::allow_origin_regex)
+    /// or `deny_origin_regex` failed to compile, each with its error
+    InvalidRegexes(Vec<(String, regex::Error)>),
+    /// An exact origin is not a valid `Origin` header value, see
+    /// [`Origin`](crate::Origin)
+    InvalidOrigin(String),
+    /// A header name is not a valid HTTP header name
+    InvalidHeaderName(String),
+    /// A header list names the same header twice, ignoring case. Only
+    /// reported when deserializing through [`Strict`](crate::Strict).
+    DuplicateHeaderName(String),
+    /// A method is not a valid HTTP method token
+    InvalidMethod(String),
+    /// An origin, method or header list is empty, so the corresponding
+    /// requests are always denied
+    EmptyList {
+        /// The config field holding the empty list
+        field: &'static str,
+    },
+    /// Credentials are allowed, but `allowed-headers` is a list with neither
+    /// `Authorization` nor `Content-Type`, which credentialed requests
+    /// usually send
+    CredentialsWithoutAuthHeaders,
+    /// Private network access is allowed for every origin, letting any
+    /// website reach the private network
+    PrivateNetworkWithAnyOrigin,
+    /// `vary` includes `Origin` although every origin gets the same `*`
+    /// response
+    VaryOriginWithAnyOrigin,
+    /// A `max-age` is longer than
+    /// [`MAX_BROWSER_MAX_AGE`](crate::MAX_BROWSER_MAX_AGE), so browsers will
+    /// cache the preflight for less time than configured
+    MaxAgeTooLong(std::time::Duration),
+    /// Methods outside of [`STANDARD_METHODS`](crate::STANDARD_METHODS) are allowed
+    NonStandardMethods(Vec<String>),
+    /// Regex origin patterns contain a `.` that matches any character where a
+    /// literal dot was probably meant, see
+    /// [`Config::validate_origin_patterns`](crate::Config::validate_origin_patterns)
+    UnescapedDots(Vec<String>),
+    /// A config file could not be read
+    Io(std::io::Error),
+    /// A TOML document could not be parsed into a config
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    /// A JSON document could not be parsed into a config
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// A YAML document could not be parsed into a config
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    /// A JSON5 document could not be parsed into a config
+    #[cfg(feature = "json5")]
+    Json5(json5::Error),
+    /// A config file's extension doesn't name a format enabled by the crate
+    /// features
+    UnsupportedFormat(std::path::PathBuf),
+    /// An environment variable read by `Config::from_env` has an invalid value
+    #[cfg(feature = "env")]
+    InvalidEnvVar {
+        /// The full name of the variable
+        name: String,
+        /// The offending value
+        value: String,
+    },
+    /// `allow-credentials` is enabled together with a wildcard (`*`) value,
+    /// which the CORS spec forbids and tower-http panics on
+    CredentialsWithWildcard {
+        /// The config field holding the wildcard
+        field: &'static str,
+    },
+    /// Origins decided by an [`OriginPredicate`](crate::OriginPredicate) or
+    /// read from [`SharedOrigins`](crate::SharedOrigins) have no serialized
+    /// form
+    DynamicOrigins,
+    /// `allowed-headers` holds [`AllowedHeaders::Regex`] patterns or an
+    /// [`AllowedHeaders::MirrorCapped`] cap, which a plain `CorsLayer` can't
+    /// filter requested headers by; build a
+    /// [`ConfigLayer`](crate::ConfigLayer) instead
+    ///
+    /// [`AllowedHeaders::Regex`]: crate::AllowedHeaders::Regex
+    /// [`AllowedHeaders::MirrorCapped`]: crate::AllowedHeaders::MirrorCapped
+    HeadersNeedConfigLayer,
+    /// A config file names a `version` this crate doesn't know, see
+    /// [`CONFIG_VERSION`](crate::CONFIG_VERSION)
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidRegex(error) => write!(f, "invalid origin pattern: {error}"),
+            ConfigError::InvalidRegexes(errors) => write!(
+                f,
+                "invalid origin patterns {}",
+                errors
+                    .iter()
+                    .map(|(pattern, _)| format!("`{pattern}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            // browsers always send the scheme, so a bare host can never match
+            ConfigError::InvalidOrigin(origin) if !origin.contains("://") => write!(
+                f,
+                "invalid origin `{origin}`, it needs a scheme such as `https://{origin}`"
+            ),
+            ConfigError::InvalidOrigin(origin) => write!(
+                f,
+                "invalid origin `{origin}`, expected `scheme://host[:port]` without a path"
+            ),
+            ConfigError::InvalidHeaderName(name) => write!(f, "invalid header name `{name}`"),
+            ConfigError::DuplicateHeaderName(name) => write!(f, "duplicate header name `{name}`"),
+            ConfigError::InvalidMethod(method) => write!(f, "invalid HTTP method `{method}`"),
+            ConfigError::EmptyList { field } => {
+                write!(
+                    f,
+                    "`{field}` is empty, so matching requests are always denied"
+                )
+            }
+            ConfigError::CredentialsWithoutAuthHeaders => write!(
+                f,
+                "`allow-credentials` is enabled but `allowed-headers` lists neither \
+                 `authorization` nor `content-type`, so browsers will block requests sending them"
+            ),
+            ConfigError::PrivateNetworkWithAnyOrigin => write!(
+                f,
+                "`allow-private-network: true` lets every origin reach the private network; \
+                 list trusted `allowed-origins` instead"
+            ),
+            ConfigError::VaryOriginWithAnyOrigin => write!(
+                f,
+                "`vary` includes `origin` but `allowed-origins: any` responds with `*` regardless of origin"
+            ),
+            ConfigError::MaxAgeTooLong(max_age) => write!(
+                f,
+                "`max-age` of {}s is longer than browsers cache preflights (at most {}s)",
+                max_age.as_secs(),
+                crate::MAX_BROWSER_MAX_AGE.as_secs()
+            ),
+            ConfigError::NonStandardMethods(methods) => {
+                write!(f, "non-standard HTTP methods: {}", methods.join(", "))
+            }
+            ConfigError::UnescapedDots(patterns) => write!(
+                f,
+                "origin patterns with an unescaped `.`, which matches any character \
+                 (escape it as `\\.`): {}",
+                patterns.join(", ")
+            ),
+            ConfigError::Io(error) => write!(f, "failed to read config: {error}"),
+            #[cfg(feature = "toml")]
+            ConfigError::Toml(error) => write!(f, "invalid TOML config: {error}"),
+            #[cfg(feature = "json")]
+            ConfigError::Json(error) => write!(f, "invalid JSON config: {error}"),
+            #[cfg(feature = "yaml")]
+            ConfigError::Yaml(error) => write!(f, "invalid YAML config: {error}"),
+            #[cfg(feature = "json5")]
+            ConfigError::Json5(error) => write!(f, "invalid JSON5 config: {error}"),
+            ConfigError::UnsupportedFormat(path) => write!(
+                f,
+                "can't tell the format of `{}` from its extension",
+                path.display()
+            ),
+            #[cfg(feature = "env")]
+            ConfigError::InvalidEnvVar { name, value } => {
+                write!(f, "invalid value `{value}` for `{name}`")
+            }
+            ConfigError::DynamicOrigins => {
+                write!(f, "dynamic or shared origins can't be serialized")
+            }
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported config version {version}, expected 1 through {}",
+                crate::CONFIG_VERSION
+            ),
+            ConfigError::HeadersNeedConfigLayer => write!(
+                f,
+                "`allowed-headers` patterns and caps need a `ConfigLayer`, tower-http's \
+                 `CorsLayer` can't filter requested headers by them"
+            ),
+            ConfigError::CredentialsWithWildcard { field } => {
+                write!(
+                    f,
+                    "cannot combine `allow-credentials: true` with `{field}: any`"
+                )?;
+                match *field {
+                    // Fetch ignores `Access-Control-Expose-Headers: *` on
+                    // credentialed responses, so there is nothing to mirror
+                    "expose-headers" => write!(f, "; list the headers to expose instead"),
+                    "allowed-headers" => write!(
+                        f,
+                        "; with credentials browsers read `*` as a header named `*`, so use \
+                         `allowed-headers: mirror` or list the headers instead"
+                    ),
+                    _ => write!(f, "; use `{field}: mirror` to reflect the request instead"),
+                }
+            }
+        }
+    }
+}
+
+impl ConfigError {
+    /// Whether this is a likely mistake or makes the config unusable
+    pub fn severity(&self) -> Severity {
+        match self {
+            ConfigError::EmptyList { .. }
+            | ConfigError::CredentialsWithoutAuthHeaders
+            | ConfigError::PrivateNetworkWithAnyOrigin
+            | ConfigError::VaryOriginWithAnyOrigin
+            | ConfigError::MaxAgeTooLong(_)
+            | ConfigError::UnescapedDots(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::InvalidRegex(error) => Some(error),
+            ConfigError::InvalidRegexes(errors) => errors.first().map(|(_, error)| error as _),
+            ConfigError::Io(error) => Some(error),
+            #[cfg(feature = "toml")]
+            ConfigError::Toml(error) => Some(error),
+            #[cfg(feature = "json")]
+            ConfigError::Json(error) => Some(error),
+            #[cfg(feature = "yaml")]
+            ConfigError::Yaml(error) => Some(error),
+            #[cfg(feature = "json5")]
+            ConfigError::Json5(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<regex::Error> for ConfigError {
+    fn from(error: regex::Error) -> Self {
+        ConfigError::InvalidRegex(error)
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(error: std::io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::Toml(error)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for ConfigError {
+    fn from(error: serde_json::Error) -> Self {
+        ConfigError::Json(error)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(error: serde_yaml::Error) -> Self {
+        ConfigError::Yaml(error)
+    }
+}
+
+#[cfg(feature = "json5")]
+impl From<json5::Error> for ConfigError {
+    fn from(error: json5::Error) -> Self {
+        ConfigError::Json5(error)
+    }
+}
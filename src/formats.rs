@@ -0,0 +1,458 @@
+//! Helpers for loading a [`Config`] from common config file formats
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders, MaxAge,
+    OriginMaxAge, SerdeRegexSet, Vary,
+};
+
+#[cfg(feature = "toml")]
+impl Config {
+    /// Parse a config from a TOML document
+    pub fn from_toml_str(toml: &str) -> Result<Config, ConfigError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Read and parse a config from a TOML file
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        Config::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(feature = "json")]
+impl Config {
+    /// Parse a config from a JSON document
+    pub fn from_json_str(json: &str) -> Result<Config, ConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read and parse a config from a JSON file
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        Config::from_json_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl Config {
+    /// Parse a config from a YAML document
+    pub fn from_yaml_str(yaml: &str) -> Result<Config, ConfigError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Read and parse a config from a YAML file
+    pub fn from_yaml_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        Config::from_yaml_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(feature = "json5")]
+impl Config {
+    /// Parse a config from a JSON5 document, which unlike JSON may contain
+    /// comments, trailing commas and unquoted keys
+    pub fn from_json5_str(json5: &str) -> Result<Config, ConfigError> {
+        Ok(json5::from_str(json5)?)
+    }
+
+    /// Read and parse a config from a JSON5 file
+    pub fn from_json5_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        Config::from_json5_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl Config {
+    /// Read and parse a config file, picking the format from its extension:
+    /// `.json`, `.json5`, `.toml`, or `.yaml`/`.yml`, each needing its crate
+    /// feature
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        parse_file(path.as_ref())
+    }
+
+    /// Load a config file and report everything wrong with it, for a
+    /// pre-deploy `config check` command.
+    ///
+    /// A file that can't be read or parsed yields that single error, since
+    /// parsing stops at the first problem. Otherwise this returns every
+    /// finding of [`Config::validate`], errors and warnings alike; each
+    /// one's [`ConfigError::severity`] tells them apart.
+    ///
+    /// ```no_run
+    /// use tower_http_cors_config::{Config, Severity};
+    ///
+    /// if let Err(problems) = Config::check_file("cors.yaml") {
+    ///     for problem in &problems {
+    ///         let label = match problem.severity() {
+    ///             Severity::Error => "error",
+    ///             Severity::Warning => "warning",
+    ///         };
+    ///         eprintln!("{label}: {problem}");
+    ///     }
+    ///     if problems.iter().any(|problem| problem.severity() == Severity::Error) {
+    ///         std::process::exit(1);
+    ///     }
+    /// }
+    /// ```
+    pub fn check_file(path: impl AsRef<Path>) -> Result<(), Vec<ConfigError>> {
+        Config::from_file(path)
+            .map_err(|error| vec![error])?
+            .validate()
+    }
+
+    /// Read each file in turn and layer it over the ones before, for setups
+    /// like `defaults.yaml` followed by `production.yaml`.
+    ///
+    /// Formats are picked as in [`Config::from_file`]. Unlike
+    /// [`Config::merge_replace`] on fully parsed configs, only the fields a
+    /// file actually sets override earlier files, so later files can be
+    /// partial. Fields that no file sets keep their [`Config::default`].
+    pub fn load_layered(paths: &[PathBuf]) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        for path in paths {
+            parse_file::<PartialConfig>(path)?.apply(&mut config);
+        }
+        Ok(config)
+    }
+}
+
+fn parse_file<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, ConfigError> {
+    let unsupported = || ConfigError::UnsupportedFormat(path.to_path_buf());
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let extension = extension.ok_or_else(unsupported)?.to_ascii_lowercase();
+    match extension.as_str() {
+        #[cfg(feature = "json")]
+        "json" => Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+        #[cfg(feature = "json5")]
+        "json5" => Ok(json5::from_str(&std::fs::read_to_string(path)?)?),
+        #[cfg(feature = "toml")]
+        "toml" => Ok(toml::from_str(&std::fs::read_to_string(path)?)?),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => Ok(serde_yaml::from_str(&std::fs::read_to_string(path)?)?),
+        _ => Err(unsupported()),
+    }
+}
+
+/// One layer of [`Config::load_layered`], where every field may be missing
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PartialConfig {
+    enabled: Option<bool>,
+    #[serde(default, deserialize_with = "crate::version::deserialize_some")]
+    version: Option<u32>,
+    allow_credentials: Option<bool>,
+    allowed_headers: Option<AllowedHeaders>,
+    allowed_methods: Option<AllowedMethods>,
+    allowed_origins: Option<AllowedOrigins>,
+    denied_origins: Option<SerdeRegexSet>,
+    allow_private_network: Option<bool>,
+    max_age: Option<MaxAge>,
+    max_age_by_origin: Option<Vec<OriginMaxAge>>,
+    expose_headers: Option<ExposeHeaders>,
+    vary: Option<Vary>,
+}
+
+impl PartialConfig {
+    fn apply(self, config: &mut Config) {
+        fn set<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        set(&mut config.enabled, self.enabled);
+        set(&mut config.version, self.version);
+        set(&mut config.allow_credentials, self.allow_credentials);
+        set(&mut config.allowed_headers, self.allowed_headers);
+        set(&mut config.allowed_methods, self.allowed_methods);
+        set(&mut config.allowed_origins, self.allowed_origins);
+        config.denied_origins = self.denied_origins.or(config.denied_origins.take());
+        set(
+            &mut config.allow_private_network,
+            self.allow_private_network,
+        );
+        set(&mut config.max_age, self.max_age);
+        set(&mut config.max_age_by_origin, self.max_age_by_origin);
+        set(&mut config.expose_headers, self.expose_headers);
+        set(&mut config.vary, self.vary);
+    }
+}
+
+/// Parses a JSON document, the same as [`Config::from_json_str`], so a
+/// config can be passed inline on the command line, e.g. with clap's
+/// `value_parser!(Config)`
+#[cfg(feature = "json")]
+impl std::str::FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(json: &str) -> Result<Config, ConfigError> {
+        Config::from_json_str(json)
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod toml_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const TOML: &str = r#"
+        allow-credentials = true
+        allowed-headers = ["authorization"]
+        allowed-methods = ["GET", "POST"]
+        allowed-origins = { exact = ["https://example.com"] }
+        max-age = "1h"
+        expose-headers = []
+    "#;
+
+    #[test]
+    fn test_from_toml_str() {
+        use std::{collections::HashSet, time::Duration};
+
+        use crate::{AllowedHeaders, AllowedMethods, AllowedOrigins};
+
+        let config = Config::from_toml_str(TOML).unwrap();
+        assert!(config.allow_credentials);
+        assert_eq!(
+            config.allowed_headers,
+            AllowedHeaders::List(HashSet::from([http::header::AUTHORIZATION]))
+        );
+        assert_eq!(
+            config.allowed_methods,
+            AllowedMethods::List(HashSet::from([http::Method::GET, http::Method::POST]))
+        );
+        assert_eq!(
+            config.allowed_origins,
+            AllowedOrigins::Exact(HashSet::from(["https://example.com".to_string()]))
+        );
+        assert_eq!(config.max_age, MaxAge::Seconds(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_from_toml_file() {
+        let path = std::env::temp_dir().join("tower-http-cors-config-test.toml");
+        std::fs::write(&path, TOML).unwrap();
+        let config = Config::from_toml_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.unwrap(), Config::from_toml_str(TOML).unwrap());
+
+        assert!(matches!(
+            Config::from_toml_file(std::env::temp_dir().join("does-not-exist.toml")),
+            Err(ConfigError::Io(_))
+        ));
+        assert!(matches!(
+            Config::from_toml_str("allow-credentials = 1"),
+            Err(ConfigError::Toml(_))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod json_tests {
+    use std::collections::HashSet;
+
+    use regex::RegexSet;
+
+    use super::*;
+    use crate::{AllowedHeaders, AllowedMethods, AllowedOrigins, SerdeRegexSet};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_json_str() {
+        let config = Config::from_json_str(
+            r#"{
+                "allowed-origins": ["https://a\\.com"],
+                "allowed-headers": "mirror",
+                "allowed-methods": ["GET"],
+                "expose-headers": []
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.allowed_origins,
+            AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://a\.com"]).unwrap()).unwrap()
+            )
+        );
+        assert_eq!(config.allowed_headers, AllowedHeaders::Mirror);
+        assert_eq!(
+            config.allowed_methods,
+            AllowedMethods::List(HashSet::from([http::Method::GET]))
+        );
+    }
+
+    #[test]
+    fn test_json_keywords_and_lists_stay_distinct() {
+        let origins = |json| serde_json::from_str::<AllowedOrigins>(json).unwrap();
+        assert_eq!(origins(r#""any""#), AllowedOrigins::Any);
+        assert_eq!(origins(r#""mirror""#), AllowedOrigins::Mirror);
+        assert_eq!(
+            origins(r#"["mirror"]"#),
+            AllowedOrigins::List(SerdeRegexSet::new(RegexSet::new(["mirror"]).unwrap()).unwrap())
+        );
+        assert_eq!(
+            origins(r#"{"exact": ["https://a.com"]}"#),
+            AllowedOrigins::Exact(HashSet::from(["https://a.com".to_string()]))
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        let json = r#"{
+            "allowed-origins": "any",
+            "allowed-headers": "any",
+            "allowed-methods": "any",
+            "expose-headers": "any"
+        }"#;
+        assert_eq!(json.parse::<Config>().unwrap(), Config::permissive());
+        assert!(matches!(
+            "allowed-origins: any".parse::<Config>(),
+            Err(ConfigError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_errors() {
+        assert!(matches!(
+            Config::from_json_str("{"),
+            Err(ConfigError::Json(_))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "json5"))]
+mod json5_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_json5_str() {
+        let config = Config::from_json5_str(
+            r#"{
+                // the storefront and its admin panel
+                "allowed-origins": {
+                    exact: ["https://shop.example", "https://admin.shop.example"],
+                },
+                "allowed-methods": ["GET", "POST",],
+                /* sessions are cookie-based */
+                "allow-credentials": true,
+                "max-age": "10m",
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                allow_credentials: true,
+                allowed_origins: AllowedOrigins::Exact(
+                    ["https://shop.example", "https://admin.shop.example"]
+                        .map(String::from)
+                        .into()
+                ),
+                allowed_methods: AllowedMethods::list([http::Method::GET, http::Method::POST]),
+                max_age: MaxAge::Seconds(std::time::Duration::from_secs(600)),
+                ..Config::default()
+            }
+        );
+        assert!(matches!(
+            Config::from_json5_str("{ max-age: }"),
+            Err(ConfigError::Json5(_))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "yaml", feature = "toml"))]
+mod layered_tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_load_layered() {
+        let dir = std::env::temp_dir().join("tower-http-cors-config-layered");
+        std::fs::create_dir_all(&dir).unwrap();
+        let defaults = dir.join("defaults.yaml");
+        let production = dir.join("production.toml");
+        std::fs::write(
+            &defaults,
+            "allowed-origins: any\nallowed-methods: [GET, POST]\nmax-age: 10m\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &production,
+            "allowed-origins = { exact = [\"https://app.example\"] }\nallow-credentials = true\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layered(&[defaults.clone(), production.clone()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            config.unwrap(),
+            Config {
+                allow_credentials: true,
+                allowed_origins: AllowedOrigins::exact("https://app.example"),
+                allowed_methods: AllowedMethods::List(HashSet::from([
+                    http::Method::GET,
+                    http::Method::POST
+                ])),
+                max_age: MaxAge::Seconds(Duration::from_secs(600)),
+                ..Config::default()
+            }
+        );
+
+        assert!(matches!(
+            Config::load_layered(&[defaults]),
+            Err(ConfigError::Io(_))
+        ));
+        assert!(matches!(
+            Config::load_layered(&[dir.join("cors.ini")]),
+            Err(ConfigError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = std::env::temp_dir().join("tower-http-cors-config-check");
+        std::fs::create_dir_all(&dir).unwrap();
+        let valid = dir.join("valid.yaml");
+        let lints = dir.join("lints.yaml");
+        let broken = dir.join("broken.toml");
+        std::fs::write(
+            &valid,
+            "allowed-origins: {exact: https://app.example}
+allowed-headers: [content-type]
+",
+        )
+        .unwrap();
+        std::fs::write(
+            &lints,
+            "allow-credentials: true
+allowed-origins: any
+allowed-headers: []
+",
+        )
+        .unwrap();
+        std::fs::write(&broken, "allowed-origins = 5\n").unwrap();
+
+        let results = [&valid, &lints, &broken].map(Config::check_file);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let [valid, lints, broken] = results;
+        assert!(valid.is_ok());
+
+        let lints = lints.unwrap_err();
+        assert!(matches!(
+            lints[0],
+            ConfigError::CredentialsWithWildcard {
+                field: "allowed-origins"
+            }
+        ));
+        assert!(lints.iter().any(|lint| matches!(
+            lint,
+            ConfigError::EmptyList {
+                field: "allowed-headers"
+            }
+        )));
+
+        assert!(matches!(broken.unwrap_err()[..], [ConfigError::Toml(_)]));
+    }
+}
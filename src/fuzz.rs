@@ -0,0 +1,235 @@
+//! [`Arbitrary`] implementations for fuzzing code that loads configs.
+//!
+//! Everything generated is valid: header names and methods are proper
+//! tokens, origins parse as [`Origin`](crate::Origin)s and regex patterns
+//! compile, so fuzzers explore real configs instead of the error paths.
+//! Patterns also come pre-anchored or flag-prefixed, and a `(?x)` pattern
+//! may end in a comment that can't be anchored; sets holding one are
+//! [`IncorrectFormat`](arbitrary::Error::IncorrectFormat).
+//! [`AllowedOrigins::Dynamic`], [`AllowedOrigins::Shared`] and
+//! `on_origin_decision` are never generated.
+
+use std::{collections::HashSet, hash::Hash, time::Duration};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use http::{HeaderName, Method};
+use regex::RegexSet;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ExposeHeaders, HeaderCap, MaxAge,
+    OriginMaxAge, SerdeRegexSet, Vary, CONFIG_VERSION, STANDARD_METHODS,
+};
+
+const HEADERS: [HeaderName; 6] = [
+    http::header::ACCEPT,
+    http::header::AUTHORIZATION,
+    http::header::CONTENT_TYPE,
+    http::header::ETAG,
+    http::header::ORIGIN,
+    http::header::RANGE,
+];
+
+/// Up to four distinct items from `item`
+fn set<T: Eq + Hash>(
+    u: &mut Unstructured<'_>,
+    mut item: impl FnMut(&mut Unstructured<'_>) -> Result<T>,
+) -> Result<HashSet<T>> {
+    let len = u.int_in_range(0..=4)?;
+    (0..len).map(|_| item(u)).collect()
+}
+
+/// A lowercase DNS label or header token of one to eight characters
+fn label(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(1..=8)?;
+    (0..len)
+        .map(|_| {
+            u.choose(b"abcdefghijklmnopqrstuvwxyz0123456789")
+                .map(|c| *c as char)
+        })
+        .collect()
+}
+
+fn header_name(u: &mut Unstructured<'_>) -> Result<HeaderName> {
+    if u.arbitrary()? {
+        return u.choose(&HEADERS).cloned();
+    }
+    Ok(HeaderName::try_from(format!("x-{}", label(u)?)).expect("labels are valid tokens"))
+}
+
+fn method(u: &mut Unstructured<'_>) -> Result<Method> {
+    if u.arbitrary()? {
+        return u.choose(&STANDARD_METHODS).cloned();
+    }
+    Ok(
+        Method::from_bytes(label(u)?.to_ascii_uppercase().as_bytes())
+            .expect("labels are valid tokens"),
+    )
+}
+
+fn origin(u: &mut Unstructured<'_>) -> Result<String> {
+    let scheme = u.choose(&["http", "https"])?;
+    let port = match u.arbitrary::<Option<u16>>()? {
+        Some(port) => format!(":{port}"),
+        None => String::new(),
+    };
+    Ok(format!("{scheme}://{}.example{port}", label(u)?))
+}
+
+impl<'a> Arbitrary<'a> for SerdeRegexSet {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let patterns = set(u, |u| {
+            let pattern = if u.arbitrary()? {
+                regex::escape(&origin(u)?)
+            } else {
+                format!(r"https://.*\.{}\.example", label(u)?)
+            };
+            Ok(match u.int_in_range(0..=5)? {
+                0 => format!("^{pattern}$"),
+                1 => format!("^+{pattern}"),
+                2 => format!("(?i){pattern}"),
+                3 => format!("(?x){pattern}"),
+                4 => format!("(?x){pattern} # {}", label(u)?),
+                _ => pattern,
+            })
+        })?;
+        let patterns = RegexSet::new(patterns).expect("generated patterns compile");
+        SerdeRegexSet::new(patterns).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for AllowedOrigins {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=5)? {
+            0 => AllowedOrigins::Any,
+            1 => AllowedOrigins::Mirror,
+            2 => AllowedOrigins::LocalhostAny,
+            3 => AllowedOrigins::Exact(set(u, origin)?),
+            4 => AllowedOrigins::Wildcard(set(u, |u| {
+                Ok(format!("https://*.{}.example", label(u)?))
+            })?),
+            _ => AllowedOrigins::List(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AllowedHeaders {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=5)? {
+            0 => AllowedHeaders::Any,
+            1 => AllowedHeaders::Mirror,
+            2 => AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers: u.arbitrary()?,
+                max_bytes: u.arbitrary()?,
+            }),
+            3 => AllowedHeaders::MirrorList(set(u, header_name)?),
+            4 => AllowedHeaders::Regex(u.arbitrary()?),
+            _ => AllowedHeaders::List(set(u, header_name)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AllowedMethods {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => AllowedMethods::Any,
+            1 => AllowedMethods::Mirror,
+            _ => AllowedMethods::List(set(u, method)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ExposeHeaders {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=3)? {
+            0 => ExposeHeaders::Any,
+            1 => ExposeHeaders::None,
+            2 => ExposeHeaders::SameAsAllowed,
+            _ => ExposeHeaders::List(set(u, header_name)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Vary {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Vary(set(u, header_name)?))
+    }
+}
+
+/// Whole seconds, since sub-second max ages are truncated when sent
+fn max_age(u: &mut Unstructured<'_>) -> Result<Duration> {
+    Ok(Duration::from_secs(u.int_in_range(0..=172_800)?))
+}
+
+impl<'a> Arbitrary<'a> for OriginMaxAge {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(OriginMaxAge {
+            origins: u.arbitrary()?,
+            max_age: max_age(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Config {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Config {
+            enabled: u.arbitrary()?,
+            version: CONFIG_VERSION,
+            allow_credentials: u.arbitrary()?,
+            allowed_headers: u.arbitrary()?,
+            allowed_methods: u.arbitrary()?,
+            allowed_origins: u.arbitrary()?,
+            denied_origins: u.arbitrary()?,
+            allow_private_network: u.arbitrary()?,
+            max_age: match u.int_in_range(0..=2)? {
+                0 => MaxAge::Disabled,
+                1 => MaxAge::Seconds(max_age(u)?),
+                _ => MaxAge::BrowserMax,
+            },
+            max_age_by_origin: (0..u.int_in_range(0..=2)?)
+                .map(|_| u.arbitrary())
+                .collect::<Result<_>>()?,
+            expose_headers: u.arbitrary()?,
+            vary: u.arbitrary()?,
+            on_origin_decision: None,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use tower_http::cors::CorsLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_configs_roundtrip() {
+        // a fixed xorshift stream keeps the test deterministic
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rejected = 0;
+        for _ in 0..200 {
+            let bytes = (0..512)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            let Ok(config) = Config::arbitrary(&mut Unstructured::new(&bytes)) else {
+                // a commented pattern that can't be anchored
+                rejected += 1;
+                continue;
+            };
+
+            let yaml = serde_yaml::to_string(&config).unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<Config>(&yaml).unwrap(),
+                config,
+                "{yaml}"
+            );
+            // invalid combinations are reported, never panicked on
+            let _ = CorsLayer::try_from(config);
+        }
+        assert!((1..100).contains(&rejected), "{rejected} rejected");
+    }
+}
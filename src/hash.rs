@@ -0,0 +1,167 @@
+//! `Hash` for the config types, so identical configs can share a layer
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet},
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ExposeHeaders, SerdeRegexSet, Vary,
+};
+
+/// Hash a set through a sorted, deduplicated view so that iteration order
+/// (and, for regex sets, pattern order) doesn't leak into the hash
+fn hash_sorted<'a, H: Hasher>(items: impl IntoIterator<Item = &'a str>, state: &mut H) {
+    let sorted: BTreeSet<&str> = items.into_iter().collect();
+    sorted.len().hash(state);
+    for item in sorted {
+        item.hash(state);
+    }
+}
+
+impl Hash for AllowedOrigins {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            AllowedOrigins::Any | AllowedOrigins::Mirror | AllowedOrigins::LocalhostAny => {}
+            AllowedOrigins::Exact(origins) | AllowedOrigins::Wildcard(origins) => {
+                hash_sorted(origins.iter().map(String::as_str), state)
+            }
+            AllowedOrigins::List(patterns) => patterns.hash(state),
+            AllowedOrigins::Dynamic(predicate) => predicate.hash(state),
+            AllowedOrigins::Shared(origins) => origins.hash(state),
+        }
+    }
+}
+
+impl Hash for AllowedHeaders {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            AllowedHeaders::Any | AllowedHeaders::Mirror => {}
+            AllowedHeaders::MirrorList(headers) | AllowedHeaders::List(headers) => {
+                hash_sorted(headers.iter().map(|header| header.as_str()), state)
+            }
+            AllowedHeaders::MirrorCapped(cap) => cap.hash(state),
+            AllowedHeaders::Regex(patterns) => patterns.hash(state),
+        }
+    }
+}
+
+impl Hash for AllowedMethods {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            AllowedMethods::Any | AllowedMethods::Mirror => {}
+            AllowedMethods::List(methods) => {
+                hash_sorted(methods.iter().map(|method| method.as_str()), state)
+            }
+        }
+    }
+}
+
+impl Hash for ExposeHeaders {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            ExposeHeaders::Any | ExposeHeaders::None | ExposeHeaders::SameAsAllowed => {}
+            ExposeHeaders::List(headers) => {
+                hash_sorted(headers.iter().map(|header| header.as_str()), state)
+            }
+        }
+    }
+}
+
+impl Hash for SerdeRegexSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_sorted(self.patterns().iter().map(String::as_str), state)
+    }
+}
+
+impl Hash for Vary {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_sorted(self.0.iter().map(|header| header.as_str()), state)
+    }
+}
+
+impl Config {
+    /// A hash of every field, including origin patterns, for deduplicating
+    /// configs or keying a cache of built layers.
+    ///
+    /// Equal configs always hash the same within one build of the program;
+    /// don't persist the value across versions.
+    pub fn config_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use http::{header, Method};
+    use regex::RegexSet;
+
+    use super::*;
+
+    fn hash_of(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_equal_configs_hash_equal() {
+        let config = || Config {
+            allowed_headers: AllowedHeaders::List(HashSet::from([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::RANGE,
+            ])),
+            allowed_methods: AllowedMethods::List(HashSet::from([Method::GET, Method::POST])),
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://a\.example", r"https://b\.example"]).unwrap(),
+                )
+                .unwrap(),
+            ),
+            ..Config::permissive()
+        };
+        assert_eq!(config().config_hash(), config().config_hash());
+
+        let reordered = Config {
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://b\.example", r"https://a\.example"]).unwrap(),
+                )
+                .unwrap(),
+            ),
+            ..config()
+        };
+        assert_eq!(reordered, config());
+        assert_eq!(reordered.config_hash(), config().config_hash());
+    }
+
+    #[test]
+    fn test_different_configs_hash_differently() {
+        let config = Config::permissive();
+        let patterns = Config {
+            allowed_origins: AllowedOrigins::regex(r"https://a\.example").unwrap(),
+            ..config.clone()
+        };
+        let other_patterns = Config {
+            allowed_origins: AllowedOrigins::regex(r"https://b\.example").unwrap(),
+            ..config.clone()
+        };
+        assert_ne!(config.config_hash(), patterns.config_hash());
+        assert_ne!(patterns.config_hash(), other_patterns.config_hash());
+        // same contents, different variant
+        assert_ne!(
+            hash_of(&AllowedHeaders::List(HashSet::new())),
+            hash_of(&AllowedHeaders::MirrorList(HashSet::new()))
+        );
+    }
+}
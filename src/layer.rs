@@ -0,0 +1,270 @@
+//! A layer for the parts of a [`Config`] tower-http can't handle itself
+
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header::ACCESS_CONTROL_REQUEST_HEADERS, HeaderName, HeaderValue, Request};
+use regex::RegexSet;
+use tower_http::cors::{AllowHeaders, Cors, CorsLayer};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{AllowedHeaders, Config, ConfigError, HeaderCap};
+
+/// A [`CorsLayer`] built from a [`Config`], which also supports
+/// [`AllowedHeaders::MirrorCapped`] and [`AllowedHeaders::Regex`], and
+/// mirrors only the allowed headers of an [`AllowedHeaders::MirrorList`].
+///
+/// tower-http can only mirror all requested headers, so for these a filter
+/// runs in front of it: names in `Access-Control-Request-Headers` that match
+/// no pattern, or aren't in the list, are dropped, as is the whole header
+/// once it is over the cap, and tower-http mirrors what is left. Browsers
+/// then reject preflights asking for any other header, since it is missing
+/// from `Access-Control-Allow-Headers`. For every other config this is just
+/// the `CorsLayer`.
+///
+/// ```
+/// use regex::RegexSet;
+/// use tower_http_cors_config::{AllowedHeaders, Config, ConfigLayer, SerdeRegexSet};
+///
+/// let layer = ConfigLayer::try_from(Config {
+///     allowed_headers: AllowedHeaders::Regex(SerdeRegexSet::new(
+///         RegexSet::new(["x-myapp-.*"]).unwrap(),
+///     ).unwrap()),
+///     ..Config::default()
+/// })
+/// .unwrap();
+/// let app = axum::Router::<()>::new().layer(layer);
+/// # let _ = app;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    cors: CorsLayer,
+    headers: Option<Arc<HeaderFilter>>,
+}
+
+/// Which requested headers [`FilterRequestHeaders`] keeps
+#[derive(Debug)]
+enum HeaderFilter {
+    Cap {
+        max_headers: usize,
+        max_bytes: usize,
+    },
+    Names(HashSet<HeaderName>),
+    Patterns(RegexSet),
+}
+
+impl TryFrom<Config> for ConfigLayer {
+    type Error = ConfigError;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let headers = match &config.allowed_headers {
+            &AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers,
+                max_bytes,
+            }) => Some(HeaderFilter::Cap {
+                max_headers,
+                max_bytes,
+            }),
+            AllowedHeaders::MirrorList(headers) => Some(HeaderFilter::Names(headers.clone())),
+            AllowedHeaders::Regex(patterns) => Some(HeaderFilter::Patterns(patterns.anchored())),
+            _ => None,
+        };
+        let mut cors = crate::build_layer(config)?;
+        if headers.is_some() {
+            cors = cors.allow_headers(AllowHeaders::mirror_request());
+        }
+        Ok(ConfigLayer {
+            cors,
+            headers: headers.map(Arc::new),
+        })
+    }
+}
+
+impl<S> Layer<S> for ConfigLayer {
+    type Service = FilterRequestHeaders<Cors<S>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FilterRequestHeaders {
+            inner: self.cors.layer(inner),
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// The service of a [`ConfigLayer`], dropping requested headers that aren't
+/// allowed before calling the CORS service
+#[derive(Debug, Clone)]
+pub struct FilterRequestHeaders<S> {
+    inner: S,
+    headers: Option<Arc<HeaderFilter>>,
+}
+
+impl<S, B> Service<Request<B>> for FilterRequestHeaders<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some(filter) = &self.headers {
+            filter_requested_headers(request.headers_mut(), filter);
+        }
+        self.inner.call(request)
+    }
+}
+
+fn filter_requested_headers(headers: &mut http::HeaderMap, filter: &HeaderFilter) {
+    let values = headers.get_all(ACCESS_CONTROL_REQUEST_HEADERS);
+    let requested = values
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim);
+    let allowed = match filter {
+        &HeaderFilter::Cap {
+            max_headers,
+            max_bytes,
+        } => {
+            // the cheap check first, and nothing allocated for either
+            let bytes = values.iter().map(HeaderValue::len).sum::<usize>();
+            if bytes <= max_bytes
+                && requested.filter(|name| !name.is_empty()).count() <= max_headers
+            {
+                return;
+            }
+            Vec::new()
+        }
+        HeaderFilter::Names(names) => requested
+            .map(str::to_ascii_lowercase)
+            .filter(|name| {
+                HeaderName::from_bytes(name.as_bytes()).is_ok_and(|name| names.contains(&name))
+            })
+            .collect(),
+        HeaderFilter::Patterns(patterns) => requested
+            .map(str::to_ascii_lowercase)
+            .filter(|name| patterns.is_match(name))
+            .collect(),
+    };
+    headers.remove(ACCESS_CONTROL_REQUEST_HEADERS);
+    if !allowed.is_empty() {
+        let value = HeaderValue::try_from(allowed.join(","))
+            .expect("names taken from a valid header value");
+        headers.insert(ACCESS_CONTROL_REQUEST_HEADERS, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::{self, ACCESS_CONTROL_ALLOW_HEADERS};
+
+    use super::*;
+    use crate::{
+        tests::{cors_headers, preflight_from},
+        AllowedOrigins, SerdeRegexSet,
+    };
+
+    fn preflight_with(allowed_headers: AllowedHeaders, headers: &str) -> http::HeaderMap {
+        let layer = ConfigLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Any,
+            allowed_headers,
+            ..Config::default()
+        })
+        .unwrap();
+        let mut request = preflight_from("https://example.com");
+        request.headers_mut().insert(
+            ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_str(headers).unwrap(),
+        );
+        cors_headers(layer, request)
+    }
+
+    fn preflight(headers: &str) -> http::HeaderMap {
+        preflight_with(
+            AllowedHeaders::Regex(
+                SerdeRegexSet::new(RegexSet::new(["x-myapp-.*"]).unwrap()).unwrap(),
+            ),
+            headers,
+        )
+    }
+
+    #[test]
+    fn test_regex_headers() {
+        assert_eq!(
+            preflight("X-MyApp-Trace")[ACCESS_CONTROL_ALLOW_HEADERS],
+            "x-myapp-trace"
+        );
+        assert_eq!(
+            preflight("x-myapp-trace, x-other")[ACCESS_CONTROL_ALLOW_HEADERS],
+            "x-myapp-trace"
+        );
+        assert!(!preflight("x-other").contains_key(ACCESS_CONTROL_ALLOW_HEADERS));
+        // anchored like origin patterns
+        assert!(!preflight("x-other-x-myapp-trace").contains_key(ACCESS_CONTROL_ALLOW_HEADERS));
+    }
+
+    #[test]
+    fn test_mirror_list_headers() {
+        let allowed = || AllowedHeaders::MirrorList(HashSet::from([header::RANGE, header::ACCEPT]));
+        assert_eq!(
+            preflight_with(allowed(), "range, x-foo")[ACCESS_CONTROL_ALLOW_HEADERS],
+            "range"
+        );
+        assert_eq!(
+            preflight_with(allowed(), "Accept,Range")[ACCESS_CONTROL_ALLOW_HEADERS],
+            "accept,range"
+        );
+        assert!(!preflight_with(allowed(), "x-foo").contains_key(ACCESS_CONTROL_ALLOW_HEADERS));
+    }
+
+    #[test]
+    fn test_mirror_capped_headers() {
+        let capped = || {
+            AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers: 2,
+                max_bytes: 12,
+            })
+        };
+        // at the cap: two names in exactly twelve bytes
+        assert_eq!(
+            preflight_with(capped(), "x-aa, x-bbbb")[ACCESS_CONTROL_ALLOW_HEADERS],
+            "x-aa, x-bbbb"
+        );
+        // over it, by count or by length
+        assert!(!preflight_with(capped(), "x-a,x-b,x-c").contains_key(ACCESS_CONTROL_ALLOW_HEADERS));
+        assert!(
+            !preflight_with(capped(), "x-aaa, x-bbbb").contains_key(ACCESS_CONTROL_ALLOW_HEADERS)
+        );
+    }
+
+    #[test]
+    fn test_cors_layer_rejects_regex_headers() {
+        let config = Config {
+            allowed_headers: AllowedHeaders::Regex(
+                SerdeRegexSet::new(RegexSet::new(["x-myapp-.*"]).unwrap()).unwrap(),
+            ),
+            ..Config::default()
+        };
+        assert!(matches!(
+            CorsLayer::try_from(config),
+            Err(ConfigError::HeadersNeedConfigLayer)
+        ));
+        let config = Config {
+            allowed_headers: AllowedHeaders::MirrorCapped(HeaderCap::default()),
+            ..Config::default()
+        };
+        assert!(matches!(
+            CorsLayer::try_from(config),
+            Err(ConfigError::HeadersNeedConfigLayer)
+        ));
+    }
+}
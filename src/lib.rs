@@ -1,40 +1,603 @@
-use std::{collections::HashSet, time::Duration};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
 
 use regex::RegexSet;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg(feature = "binary")]
+mod binary;
+mod builder;
+mod config_set;
+mod describe;
+mod diff;
+#[cfg(feature = "env")]
+mod env;
+mod error;
+#[cfg(any(
+    feature = "json",
+    feature = "json5",
+    feature = "toml",
+    feature = "yaml"
+))]
+mod formats;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod hash;
+mod layer;
+mod matcher;
+mod max_age;
+mod merge;
+mod normalize;
+mod origin;
+pub mod prelude;
+mod presets;
+#[cfg(feature = "schemars")]
+mod schema;
+mod shared;
+mod static_config;
+#[cfg(feature = "serde")]
+mod strict;
+#[cfg(feature = "testing")]
+mod testing;
+mod validate;
+mod version;
+
+#[cfg(feature = "binary")]
+pub use binary::{
+    BinaryConfig, BinaryExposeHeaders, BinaryHeaders, BinaryMaxAge, BinaryMethods, BinaryOrigins,
+};
+pub use builder::ConfigBuilder;
+pub use config_set::ConfigSet;
+pub use diff::FieldDiff;
+pub use error::ConfigError;
+pub use layer::{ConfigLayer, FilterRequestHeaders};
+pub use matcher::OriginMatcher;
+pub use max_age::MaxAge;
+pub use origin::Origin;
+pub use shared::SharedOrigins;
+pub use static_config::StaticConfig;
+#[cfg(feature = "serde")]
+pub use strict::Strict;
+#[cfg(feature = "testing")]
+pub use testing::{AlwaysOk, ObservedCors, PreflightResult};
+pub use validate::{Severity, Strictness, MAX_BROWSER_MAX_AGE, STANDARD_METHODS};
+pub use version::CONFIG_VERSION;
+
+/// Controls the `Access-Control-Allow-Origin` response header.
+///
+/// In config files, the shape of the value alone picks the variant:
+///
+/// - a string that is exactly one of the lowercase keywords, `any` or
+///   `mirror`, is that keyword.
+/// - any other string, including `Any`, is a one-pattern
+///   [`AllowedOrigins::List`].
+/// - a sequence is always a [`AllowedOrigins::List`], even if its only entry
+///   is `any` or `mirror`.
+/// - a map with a single `exact` or `wildcard` key is
+///   [`AllowedOrigins::Exact`] or [`AllowedOrigins::Wildcard`], again with a
+///   string or a sequence of strings.
+///
+/// [`AllowedHeaders`], [`AllowedMethods`] and [`ExposeHeaders`] follow the
+/// same rules with their own keywords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum AllowedOrigins {
+    /// Allow every origin by responding with `*`. Can't be combined with
+    /// credentials; see [`AllowedOrigins::Mirror`] for that.
     Any,
+    /// Allow every origin by echoing the request's `Origin` back.
+    ///
+    /// This is how to allow any origin *with* credentials, which the spec
+    /// forbids for `*`.
+    ///
+    /// **Security:** together with `allow-credentials: true`, any website a
+    /// user visits can make authenticated requests with their cookies and
+    /// read the responses. Only use it for APIs that are meant to be
+    /// called with credentials from anywhere, and prefer a list otherwise.
     Mirror,
+    /// Allow any loopback origin on any port, for local development:
+    /// `http` or `https` with a host of `localhost`, `127.0.0.1` or `[::1]`.
+    /// Written as `any-localhost` in config files.
+    #[cfg_attr(feature = "serde", serde(rename = "any-localhost"))]
+    LocalhostAny,
+    /// Decide with custom code that can also look at the request, e.g. to
+    /// allow an origin only on some paths.
+    ///
+    /// Only constructible programmatically: it is skipped when deserializing
+    /// and fails to serialize.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Dynamic(OriginPredicate),
+    /// Allow origins matching a regex list that can be replaced at runtime,
+    /// see [`SharedOrigins`].
+    ///
+    /// Only constructible programmatically, like
+    /// [`AllowedOrigins::Dynamic`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Shared(SharedOrigins),
+    /// Allow only these origins, compared as exact, case-sensitive strings.
+    ///
+    /// Unlike [`AllowedOrigins::List`], no regex metacharacters are involved,
+    /// so `https://example.com` can't accidentally match `https://exampleXcom`.
+    /// Written as `exact: [...]` in config files, where every entry must be a
+    /// valid [`Origin`].
+    ///
+    /// Browsers send international domain names in their ASCII (punycode)
+    /// form. With the `idna` feature, Unicode hosts here and in
+    /// [`AllowedOrigins::Wildcard`] are converted to match; otherwise write
+    /// them as punycode, e.g. `https://xn--bcher-kva.example`.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Exact(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "serde_tagged_origins::serialize_exact")
+        )]
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::ExactOrigins"))]
+        HashSet<String>,
+    ),
+    /// Allow origins matching simple wildcard patterns such as
+    /// `https://*.example.com`.
+    ///
+    /// Each `*` matches exactly one DNS label (letters, digits and `-`), so the
+    /// pattern above matches `https://api.example.com` but neither
+    /// `https://example.com` nor `https://a.b.example.com`. Everything else is
+    /// matched literally. Written as `wildcard: [...]` in config files.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Wildcard(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "serde_tagged_origins::serialize_wildcard")
+        )]
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::WildcardOrigins"))]
+        HashSet<String>,
+    ),
+    /// Allow origins matching any of the given regular expressions.
+    ///
+    /// Each pattern must match the *entire* origin: patterns are wrapped in
+    /// `^(?:...)$` before matching, so `https://example\.com` will not match
+    /// `https://example.com.evil.com`. Configs that relied on substring
+    /// matches need to add an explicit `.*` where they want one.
+    ///
+    /// A matching origin is echoed back rather than answered with `*`, and
+    /// the same goes for [`Exact`](AllowedOrigins::Exact),
+    /// [`Wildcard`](AllowedOrigins::Wildcard) and
+    /// [`LocalhostAny`](AllowedOrigins::LocalhostAny). Lists therefore work
+    /// with credentials as they are, which is the usual way to allow
+    /// credentials from several origins; there is no separate mirroring
+    /// mode for them.
     #[cfg_attr(feature = "serde", serde(untagged))]
     List(SerdeRegexSet),
 }
 
-impl From<AllowedOrigins> for tower_http::cors::AllowOrigin {
+impl AllowedOrigins {
+    /// Allow no origins at all
+    pub fn none() -> AllowedOrigins {
+        AllowedOrigins::default()
+    }
+
+    /// Allow only `origin`, compared exactly
+    pub fn exact(origin: impl Into<String>) -> AllowedOrigins {
+        AllowedOrigins::Exact(HashSet::from([origin.into()]))
+    }
+
+    /// Allow `hosts` over `http` or `https`, with or without any port.
+    ///
+    /// Each host becomes the regex `https?://<host>(?::[0-9]{1,5})?` with the
+    /// host escaped, anchored like every [`AllowedOrigins::List`] pattern. So
+    /// `app.example.com` matches `http://app.example.com` and
+    /// `https://app.example.com:8443`, but not `https://api.app.example.com`.
+    /// Write international hosts in punycode.
+    pub fn host_only(hosts: &[&str]) -> AllowedOrigins {
+        AllowedOrigins::try_list(
+            hosts
+                .iter()
+                .map(|host| format!("https?://{}(?::[0-9]{{1,5}})?", regex::escape(host))),
+        )
+        .expect("escaped hosts are valid regexes")
+    }
+
+    /// Allow origins matching the regex `pattern`
+    pub fn regex(pattern: &str) -> Result<AllowedOrigins, regex::Error> {
+        AllowedOrigins::regexes(&[pattern])
+    }
+
+    /// Allow origins matching any of the regex `patterns`
+    pub fn regexes(patterns: &[&str]) -> Result<AllowedOrigins, regex::Error> {
+        AllowedOrigins::try_list(patterns)
+    }
+
+    /// Compile `patterns` into an [`AllowedOrigins::List`], reporting an
+    /// invalid pattern now rather than when the config is loaded.
+    ///
+    /// ```
+    /// # use tower_http_cors_config::AllowedOrigins;
+    /// let origins = AllowedOrigins::try_list([r"https://.*\.example\.com"]).unwrap();
+    /// assert!(origins.matcher().is_match_str("https://api.example.com"));
+    ///
+    /// assert!(AllowedOrigins::try_list(vec!["https://(".to_string()]).is_err());
+    /// ```
+    pub fn try_list(
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<AllowedOrigins, regex::Error> {
+        Ok(AllowedOrigins::List(SerdeRegexSet::new(RegexSet::new(
+            patterns,
+        )?)?))
+    }
+
+    /// How many origins or patterns are listed, or `None` for policies
+    /// without a list: `Any`, `Mirror`, `LocalhostAny` and `Dynamic`. A
+    /// `Shared` list counts its patterns at the time of the call.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            AllowedOrigins::Any
+            | AllowedOrigins::Mirror
+            | AllowedOrigins::LocalhostAny
+            | AllowedOrigins::Dynamic(_) => None,
+            AllowedOrigins::Shared(origins) => Some(origins.patterns().len()),
+            AllowedOrigins::Exact(origins) | AllowedOrigins::Wildcard(origins) => {
+                Some(origins.len())
+            }
+            AllowedOrigins::List(patterns) => Some(patterns.len()),
+        }
+    }
+
+    /// Whether this is a list with nothing in it, so no origin is allowed
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+/// The callback behind [`AllowedOrigins::Dynamic`], given the `Origin` and
+/// the rest of the request.
+///
+/// Predicates compare equal (and hash the same) only if they are clones of
+/// the same `Arc`.
+///
+/// Predicates are synchronous: tower-http 0.5 has no async origin check, so
+/// there is no async variant either. For allowlists kept in a database, keep
+/// a copy in memory that a background task refreshes, and read that here.
+/// The predicate runs on every request from any thread, hence
+/// `Send + Sync + 'static`.
+///
+/// ```
+/// use std::{
+///     collections::HashSet,
+///     sync::{Arc, RwLock},
+/// };
+///
+/// use tower_http_cors_config::{AllowedOrigins, OriginPredicate};
+///
+/// let tenants = Arc::new(RwLock::new(HashSet::from([
+///     "https://acme.example".to_string(),
+/// ])));
+/// // a background task would replace the set whenever the database changes
+/// let allowed = Arc::clone(&tenants);
+/// let origins = AllowedOrigins::Dynamic(OriginPredicate::new(move |origin, _parts| {
+///     origin
+///         .to_str()
+///         .is_ok_and(|origin| allowed.read().unwrap().contains(origin))
+/// }));
+/// # let _ = origins;
+/// ```
+#[derive(Clone)]
+pub struct OriginPredicate(Arc<OriginPredicateFn>);
+
+type OriginPredicateFn = dyn Fn(&http::HeaderValue, &http::request::Parts) -> bool + Send + Sync;
+
+impl OriginPredicate {
+    /// Wrap `predicate`, which gets the `Origin` header and the rest of the
+    /// request and returns whether to allow it
+    pub fn new(
+        predicate: impl Fn(&http::HeaderValue, &http::request::Parts) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        OriginPredicate(Arc::new(predicate))
+    }
+
+    pub(crate) fn call(&self, origin: &http::HeaderValue, parts: &http::request::Parts) -> bool {
+        (self.0)(origin, parts)
+    }
+}
+
+impl fmt::Debug for OriginPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OriginPredicate").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for OriginPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for OriginPredicate {}
+
+impl std::hash::Hash for OriginPredicate {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// No origins, so every cross-origin request is rejected
+impl Default for AllowedOrigins {
+    fn default() -> Self {
+        AllowedOrigins::List(
+            SerdeRegexSet::new(RegexSet::empty()).expect("an empty set has nothing to anchor"),
+        )
+    }
+}
+
+/// With the `tracing` feature, every origin checked against an `exact`,
+/// `wildcard` or regex list is logged at `debug` level along with the outcome.
+impl From<AllowedOrigins> for AllowOrigin {
     fn from(value: AllowedOrigins) -> Self {
-        use tower_http::cors::AllowOrigin;
         match value {
             AllowedOrigins::Any => AllowOrigin::any(),
             AllowedOrigins::Mirror => AllowOrigin::mirror_request(),
-            AllowedOrigins::List(origins) => AllowOrigin::predicate(move |origin, _parts| {
-                origin.to_str().is_ok_and(|origin| origins.is_match(origin))
-            }),
+            AllowedOrigins::Dynamic(predicate) => {
+                AllowOrigin::predicate(move |origin, parts| predicate.call(origin, parts))
+            }
+            origins => {
+                let matcher = OriginMatcher::from(origins);
+                AllowOrigin::predicate(move |origin, _parts| {
+                    let allowed = matcher.is_match(origin);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(?origin, allowed, "evaluated CORS origin");
+                    allowed
+                })
+            }
+        }
+    }
+}
+
+/// Reads a sequence of strings, accepting a lone string as a one-element
+/// sequence
+#[cfg(feature = "serde")]
+mod serde_one_or_many {
+    use std::fmt;
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserialize, Deserializer,
+    };
+
+    pub struct OneOrMany(pub Vec<String>);
+
+    impl<'de> Deserialize<'de> for OneOrMany {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct OneOrManyVisitor;
+
+            impl<'de> Visitor<'de> for OneOrManyVisitor {
+                type Value = OneOrMany;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a string or a sequence of strings")
+                }
+
+                fn visit_str<E: de::Error>(self, value: &str) -> Result<OneOrMany, E> {
+                    Ok(OneOrMany(vec![value.to_string()]))
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<OneOrMany, A::Error> {
+                    let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                    while let Some(value) = seq.next_element()? {
+                        values.push(value);
+                    }
+                    Ok(OneOrMany(values))
+                }
+            }
+
+            deserializer.deserialize_any(OneOrManyVisitor)
         }
     }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        OneOrMany::deserialize(deserializer).map(|values| values.0)
+    }
+
+    /// Like [`deserialize`], but also splits each string on commas, so
+    /// `"GET, POST"` reads the same as `[GET, POST]`
+    pub fn deserialize_comma_separated<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        Ok(deserialize(deserializer)?
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Serializes a set of origin strings as a single-key map (e.g. `exact: [...]`)
+/// so it can't be confused with a list of regex patterns, independent of how
+/// the format represents enums
+#[cfg(feature = "serde")]
+mod serde_tagged_origins {
+    use std::collections::{HashMap, HashSet};
+
+    use serde::{de, ser::SerializeMap, Serializer};
+
+    use super::serde_one_or_many::OneOrMany;
+
+    fn serialize<S>(tag: &str, value: &HashSet<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut sorted: Vec<&String> = value.iter().collect();
+        sorted.sort();
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(tag, &sorted)?;
+        map.end()
+    }
+
+    pub fn serialize_exact<S: Serializer>(
+        value: &HashSet<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize("exact", value, serializer)
+    }
+
+    pub fn serialize_wildcard<S: Serializer>(
+        value: &HashSet<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize("wildcard", value, serializer)
+    }
+
+    /// The `exact` or `wildcard` variant for a single-key map, checking
+    /// every exact entry with [`Origin::parse`](crate::Origin::parse)
+    pub fn from_map<E: de::Error>(
+        mut map: HashMap<String, OneOrMany>,
+    ) -> Result<crate::AllowedOrigins, E> {
+        let origins = |origins: OneOrMany| origins.0.into_iter().collect::<HashSet<_>>();
+        match (map.remove("exact"), map.remove("wildcard")) {
+            (Some(exact), None) if map.is_empty() => {
+                let origins = origins(exact);
+                for origin in &origins {
+                    crate::Origin::parse(origin).map_err(E::custom)?;
+                }
+                Ok(crate::AllowedOrigins::Exact(origins))
+            }
+            (None, Some(wildcard)) if map.is_empty() => {
+                Ok(crate::AllowedOrigins::Wildcard(origins(wildcard)))
+            }
+            _ => Err(E::custom(
+                "expected a map with a single `exact` or `wildcard` key",
+            )),
+        }
+    }
+}
+
+/// Deserialized by hand rather than derived: with untagged variants, serde
+/// reports any mistake as "data did not match any variant", hiding which
+/// origin or pattern was wrong
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AllowedOrigins {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use std::{collections::HashMap, fmt};
+
+        use serde::de::{
+            self,
+            value::{MapAccessDeserializer, SeqAccessDeserializer},
+            IntoDeserializer, MapAccess, SeqAccess, Visitor,
+        };
+
+        fn list<'de, D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<AllowedOrigins, D::Error> {
+            let patterns = serde_regex_set::deserialize(deserializer)?;
+            SerdeRegexSet::new(patterns)
+                .map(AllowedOrigins::List)
+                .map_err(|error| de::Error::custom(ConfigError::from(error)))
+        }
+
+        struct OriginsVisitor;
+
+        impl<'de> Visitor<'de> for OriginsVisitor {
+            type Value = AllowedOrigins;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(
+                    "`any`, `mirror`, `any-localhost`, regex patterns, or a map with a \
+                     single `exact` or `wildcard` key",
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<AllowedOrigins, E> {
+                match value {
+                    "any" => Ok(AllowedOrigins::Any),
+                    "mirror" => Ok(AllowedOrigins::Mirror),
+                    "any-localhost" => Ok(AllowedOrigins::LocalhostAny),
+                    pattern => list(pattern.into_deserializer()),
+                }
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<AllowedOrigins, A::Error> {
+                list(SeqAccessDeserializer::new(seq))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<AllowedOrigins, A::Error> {
+                let map: HashMap<String, serde_one_or_many::OneOrMany> =
+                    serde::Deserialize::deserialize(MapAccessDeserializer::new(map))?;
+                serde_tagged_origins::from_map(map)
+            }
+        }
+
+        deserializer.deserialize_any(OriginsVisitor)
+    }
 }
 
+/// Controls the `Access-Control-Allow-Headers` response header. Parsed like
+/// [`AllowedOrigins`]: `any` or `mirror`, a list, or a `mirror-capped`,
+/// `mirror-list` or `regex` map. Lists may also be written as one comma-separated string.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum AllowedHeaders {
     Any,
+    /// Reflect `Access-Control-Request-Headers` back, however long it is. On
+    /// public endpoints prefer [`AllowedHeaders::MirrorCapped`].
     Mirror,
+    /// Reflect `Access-Control-Request-Headers` back, unless it is over the
+    /// [`HeaderCap`], in which case no headers are allowed. Written as
+    /// `mirror-capped: { max-headers: 32, max-bytes: 1024 }` in config files.
+    ///
+    /// Like [`AllowedHeaders::Regex`], this needs a [`ConfigLayer`], which
+    /// drops an oversized `Access-Control-Request-Headers` before tower-http
+    /// mirrors it.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    MirrorCapped(
+        #[cfg_attr(feature = "serde", serde(with = "serde_mirror_capped"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::MirrorCappedHeaders"))]
+        HeaderCap,
+    ),
+    /// Mirror the requested headers, but only those in this list. Written
+    /// as `mirror-list: [...]` in config files.
+    ///
+    /// tower-http has no hook to compute `Access-Control-Allow-Headers` per
+    /// request, so a [`ConfigLayer`] drops the other names from
+    /// `Access-Control-Request-Headers` before tower-http mirrors it. A plain
+    /// `CorsLayer` responds with the whole list instead, like
+    /// [`AllowedHeaders::List`]. Browsers reach the same decision either way:
+    /// a preflight passes only if every requested header is allowed.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    MirrorList(
+        #[cfg_attr(feature = "serde", serde(with = "serde_mirror_list"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::MirrorListHeaders"))]
+        HashSet<http::HeaderName>,
+    ),
+    /// Mirror the requested headers whose names match any of these regexes,
+    /// e.g. `x-myapp-.*` for every header of an application. Like origin
+    /// patterns, each must match the entire name, and names are lowercase.
+    /// Written as `regex: [...]` in config files.
+    ///
+    /// tower-http can't filter mirrored headers itself, so this needs a
+    /// [`ConfigLayer`], which drops the other names from
+    /// `Access-Control-Request-Headers` before tower-http mirrors it.
+    /// `CorsLayer::try_from` fails with
+    /// [`ConfigError::HeadersNeedConfigLayer`], and converting the bare
+    /// value into tower-http's `AllowHeaders` allows no headers.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Regex(
+        #[cfg_attr(feature = "serde", serde(with = "serde_header_patterns"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::RegexHeaders"))]
+        SerdeRegexSet,
+    ),
     #[cfg_attr(feature = "serde", serde(untagged))]
     List(
-        #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))] HashSet<http::HeaderName>,
+        #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "HashSet<String>"))]
+        HashSet<http::HeaderName>,
     ),
 }
 
@@ -43,15 +606,19 @@ mod serde_header_name {
     use std::collections::HashSet;
 
     use http::HeaderName;
-    use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+    use serde::{de, ser::SerializeSeq, Deserializer, Serializer};
+
+    use crate::ConfigError;
 
     pub fn serialize<S>(value: &HashSet<HeaderName>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(value.len()))?;
-        for header in value {
-            seq.serialize_element(header.to_string().as_str())?;
+        let mut sorted: Vec<&str> = value.iter().map(|header| header.as_str()).collect();
+        sorted.sort_unstable();
+        let mut seq = serializer.serialize_seq(Some(sorted.len()))?;
+        for header in sorted {
+            seq.serialize_element(header)?;
         }
         seq.end()
     }
@@ -60,12 +627,198 @@ mod serde_header_name {
     where
         D: Deserializer<'de>,
     {
-        let values: Vec<String> = Deserialize::deserialize(deserializer)?;
-        values
-            .into_iter()
-            .map(HeaderName::try_from)
-            .collect::<Result<HashSet<_>, _>>()
-            .map_err(de::Error::custom)
+        let values = super::serde_one_or_many::deserialize_comma_separated(deserializer)?;
+        let strict = crate::strict::is_strict();
+        let mut headers = HashSet::with_capacity(values.len());
+        for value in values {
+            let header = HeaderName::try_from(value.as_str())
+                .map_err(|_| de::Error::custom(ConfigError::InvalidHeaderName(value.clone())))?;
+            if !headers.insert(header) && strict {
+                return Err(de::Error::custom(ConfigError::DuplicateHeaderName(value)));
+            }
+        }
+        Ok(headers)
+    }
+}
+
+/// Serializes mirrored headers as a single-key `mirror-list` map, like
+/// [`serde_tagged_origins`]
+#[cfg(feature = "serde")]
+mod serde_mirror_list {
+    use std::collections::{HashMap, HashSet};
+
+    use http::HeaderName;
+    use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+    struct Headers<'a>(&'a HashSet<HeaderName>);
+
+    impl Serialize for Headers<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serde_header_name::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedHeaders(#[serde(with = "super::serde_header_name")] HashSet<HeaderName>);
+
+    pub fn serialize<S>(value: &HashSet<HeaderName>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("mirror-list", &Headers(value))?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashSet<HeaderName>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut map: HashMap<String, OwnedHeaders> = Deserialize::deserialize(deserializer)?;
+        match map.remove("mirror-list") {
+            Some(headers) if map.is_empty() => Ok(headers.0),
+            _ => Err(de::Error::custom(
+                "expected a map with a single `mirror-list` key",
+            )),
+        }
+    }
+}
+
+/// Serializes a header cap as a single-key `mirror-capped` map, like
+/// [`serde_mirror_list`]
+#[cfg(feature = "serde")]
+mod serde_mirror_capped {
+    use std::collections::HashMap;
+
+    use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serializer};
+
+    use crate::HeaderCap;
+
+    pub fn serialize<S>(value: &HeaderCap, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("mirror-capped", value)?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HeaderCap, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut map: HashMap<String, HeaderCap> = Deserialize::deserialize(deserializer)?;
+        match map.remove("mirror-capped") {
+            Some(cap) if map.is_empty() => Ok(cap),
+            _ => Err(de::Error::custom(
+                "expected a map with a single `mirror-capped` key",
+            )),
+        }
+    }
+}
+
+/// Serializes header patterns as a single-key `regex` map, like
+/// [`serde_mirror_list`]
+#[cfg(feature = "serde")]
+mod serde_header_patterns {
+    use std::collections::HashMap;
+
+    use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serializer};
+
+    use crate::SerdeRegexSet;
+
+    pub fn serialize<S>(value: &SerdeRegexSet, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("regex", value)?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SerdeRegexSet, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut map: HashMap<String, SerdeRegexSet> = Deserialize::deserialize(deserializer)?;
+        match map.remove("regex") {
+            Some(patterns) if map.is_empty() => Ok(patterns),
+            _ => Err(de::Error::custom(
+                "expected a map with a single `regex` key",
+            )),
+        }
+    }
+}
+
+impl AllowedHeaders {
+    /// Allow no request headers beyond the CORS-safelisted ones
+    pub fn none() -> Self {
+        AllowedHeaders::default()
+    }
+
+    /// Allow exactly these request headers
+    pub fn list(headers: impl IntoIterator<Item = http::HeaderName>) -> Self {
+        AllowedHeaders::List(headers.into_iter().collect())
+    }
+
+    /// The listed headers sorted by name, or `None` for `Any`, `Mirror`,
+    /// `MirrorCapped` and `Regex`
+    pub fn as_sorted_vec(&self) -> Option<Vec<&http::HeaderName>> {
+        match self {
+            AllowedHeaders::Any
+            | AllowedHeaders::Mirror
+            | AllowedHeaders::MirrorCapped(_)
+            | AllowedHeaders::Regex(_) => None,
+            AllowedHeaders::MirrorList(headers) | AllowedHeaders::List(headers) => {
+                Some(sorted_headers(headers))
+            }
+        }
+    }
+
+    /// How many headers are listed, or `None` for `Any`, `Mirror`,
+    /// `MirrorCapped` and `Regex`
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            AllowedHeaders::Any
+            | AllowedHeaders::Mirror
+            | AllowedHeaders::MirrorCapped(_)
+            | AllowedHeaders::Regex(_) => None,
+            AllowedHeaders::MirrorList(headers) | AllowedHeaders::List(headers) => {
+                Some(headers.len())
+            }
+        }
+    }
+
+    /// Whether this is a list with nothing in it
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+/// Parses `any` or `*`, `mirror`, or a comma-separated list of header names
+impl TryFrom<&str> for AllowedHeaders {
+    type Error = ConfigError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.trim() {
+            "any" | "*" => AllowedHeaders::Any,
+            "mirror" => AllowedHeaders::Mirror,
+            list => AllowedHeaders::List(
+                split_list(list)
+                    .map(|header| {
+                        http::HeaderName::try_from(header)
+                            .map_err(|_| ConfigError::InvalidHeaderName(header.to_string()))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+/// No headers beyond the CORS-safelisted ones
+impl Default for AllowedHeaders {
+    fn default() -> Self {
+        AllowedHeaders::List(HashSet::new())
     }
 }
 
@@ -75,26 +828,146 @@ impl From<AllowedHeaders> for tower_http::cors::AllowHeaders {
         match value {
             AllowedHeaders::Any => AllowHeaders::any(),
             AllowedHeaders::Mirror => AllowHeaders::mirror_request(),
-            AllowedHeaders::List(allowed_headers) => AllowHeaders::list(allowed_headers),
+            AllowedHeaders::MirrorList(allowed_headers) | AllowedHeaders::List(allowed_headers) => {
+                AllowHeaders::list(allowed_headers)
+            }
+            // only `ConfigLayer` can filter, so fail safe
+            AllowedHeaders::MirrorCapped(_) | AllowedHeaders::Regex(_) => AllowHeaders::list([]),
+        }
+    }
+}
+
+/// How much of `Access-Control-Request-Headers` an
+/// [`AllowedHeaders::MirrorCapped`] mirrors. Either field may be left out of
+/// a config file to keep its default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "serde",
+    serde(default, rename_all = "kebab-case", deny_unknown_fields)
+)]
+pub struct HeaderCap {
+    /// The most header names to mirror, [`HeaderCap::DEFAULT_MAX_HEADERS`]
+    /// unless set
+    pub max_headers: usize,
+    /// The longest `Access-Control-Request-Headers` to mirror, in bytes,
+    /// [`HeaderCap::DEFAULT_MAX_BYTES`] unless set
+    pub max_bytes: usize,
+}
+
+impl HeaderCap {
+    /// Enough for the handful of headers a browser app sends, with room for
+    /// tracing and auth headers on top
+    pub const DEFAULT_MAX_HEADERS: usize = 32;
+
+    /// About 30 bytes for each of [`HeaderCap::DEFAULT_MAX_HEADERS`] names
+    pub const DEFAULT_MAX_BYTES: usize = 1024;
+}
+
+impl Default for HeaderCap {
+    fn default() -> Self {
+        HeaderCap {
+            max_headers: HeaderCap::DEFAULT_MAX_HEADERS,
+            max_bytes: HeaderCap::DEFAULT_MAX_BYTES,
         }
     }
 }
 
+/// Controls the `Access-Control-Allow-Methods` response header. Parsed like
+/// [`AllowedOrigins`]: `any` or `mirror`, or a list, which may also be
+/// written as one comma-separated string (`"GET, POST"`).
+///
+/// Methods in a list are case-insensitive (`get` is `GET`), and a `*` or
+/// `all` entry stands for every method in [`STANDARD_METHODS`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum AllowedMethods {
+    /// Allow all methods by responding with `*`
+    Any,
     /// Mirror the request method
     Mirror,
     /// Allow a specific list of methods
     #[cfg_attr(feature = "serde", serde(untagged))]
-    List(#[cfg_attr(feature = "serde", serde(with = "serde_method"))] HashSet<http::Method>),
+    List(
+        #[cfg_attr(feature = "serde", serde(with = "serde_method"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "HashSet<String>"))]
+        HashSet<http::Method>,
+    ),
+}
+
+impl AllowedMethods {
+    /// Allow no methods in preflights, leaving only simple requests
+    pub fn none() -> Self {
+        AllowedMethods::List(HashSet::new())
+    }
+
+    /// Allow exactly these methods
+    pub fn list(methods: impl IntoIterator<Item = http::Method>) -> Self {
+        AllowedMethods::List(methods.into_iter().collect())
+    }
+
+    /// The listed methods sorted by name, or `None` for `Any` and `Mirror`
+    pub fn as_sorted_vec(&self) -> Option<Vec<&http::Method>> {
+        match self {
+            AllowedMethods::Any | AllowedMethods::Mirror => None,
+            AllowedMethods::List(methods) => {
+                let mut methods = methods.iter().collect::<Vec<_>>();
+                methods.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+                Some(methods)
+            }
+        }
+    }
+
+    /// How many methods are listed, or `None` for `Any` and `Mirror`
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            AllowedMethods::Any | AllowedMethods::Mirror => None,
+            AllowedMethods::List(methods) => Some(methods.len()),
+        }
+    }
+
+    /// Whether this is a list with nothing in it
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+/// Parses `any` or `*`, `mirror`, or a comma-separated list of methods, read
+/// like lists in config files
+impl TryFrom<&str> for AllowedMethods {
+    type Error = ConfigError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.trim() {
+            "any" | "*" => AllowedMethods::Any,
+            "mirror" => AllowedMethods::Mirror,
+            list => AllowedMethods::List(parse_methods(split_list(list))?),
+        })
+    }
+}
+
+/// The trimmed, non-empty entries of a comma-separated list
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+/// `GET` and `HEAD`, the methods that can't change anything on the server
+impl Default for AllowedMethods {
+    fn default() -> Self {
+        AllowedMethods::List(HashSet::from([http::Method::GET, http::Method::HEAD]))
+    }
 }
 
 impl From<AllowedMethods> for tower_http::cors::AllowMethods {
     fn from(value: AllowedMethods) -> Self {
         use tower_http::cors::AllowMethods;
         match value {
+            AllowedMethods::Any => AllowMethods::any(),
             AllowedMethods::Mirror => AllowMethods::mirror_request(),
             AllowedMethods::List(methods) => AllowMethods::list(methods),
         }
@@ -105,15 +978,17 @@ mod serde_method {
     use std::collections::HashSet;
 
     use http::Method;
-    use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+    use serde::{de, ser::SerializeSeq, Deserializer, Serializer};
 
     pub fn serialize<S>(value: &HashSet<Method>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(value.len()))?;
-        for header in value {
-            seq.serialize_element(header.to_string().as_str())?;
+        let mut sorted: Vec<&str> = value.iter().map(|header| header.as_str()).collect();
+        sorted.sort_unstable();
+        let mut seq = serializer.serialize_seq(Some(sorted.len()))?;
+        for header in sorted {
+            seq.serialize_element(header)?;
         }
         seq.end()
     }
@@ -122,63 +997,303 @@ mod serde_method {
     where
         D: Deserializer<'de>,
     {
-        use std::str::FromStr;
-        let values: Vec<String> = Deserialize::deserialize(deserializer)?;
-        values
-            .into_iter()
-            .map(|value| Method::from_str(&value).map_err(de::Error::custom))
-            .collect()
+        let values = super::serde_one_or_many::deserialize_comma_separated(deserializer)?;
+        super::parse_methods(values.iter().map(String::as_str)).map_err(de::Error::custom)
+    }
+}
+
+/// Parse method names case-insensitively, expanding `*` and `all` to
+/// [`STANDARD_METHODS`].
+///
+/// Extension methods are uppercased too, so `purge` becomes `PURGE`.
+pub(crate) fn parse_methods<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+) -> Result<HashSet<http::Method>, ConfigError> {
+    let mut methods = HashSet::new();
+    for value in values {
+        if value == "*" || value.eq_ignore_ascii_case("all") {
+            methods.extend(STANDARD_METHODS);
+        } else {
+            let method = http::Method::from_bytes(value.to_ascii_uppercase().as_bytes())
+                .map_err(|_| ConfigError::InvalidMethod(value.to_string()))?;
+            methods.insert(method);
+        }
     }
+    Ok(methods)
 }
 
+/// Controls the `Access-Control-Expose-Headers` response header. Parsed like
+/// [`AllowedOrigins`]: `any`, `none` or `same-as-allowed`, or a list.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ExposeHeaders {
     /// Expose all headers by responding with `*`
     Any,
+    /// Deliberately expose nothing beyond the CORS-safelisted response
+    /// headers. Behaves like an empty [`ExposeHeaders::List`], but states the
+    /// intent and is written as `none` in config files.
+    None,
+    /// Expose the same headers that [`Config::allowed_headers`] allows, so
+    /// the two lists can't drift apart. Written as `same-as-allowed` in
+    /// config files.
+    ///
+    /// Both `List` and `MirrorList` allowed headers are copied. `Any` and
+    /// `Mirror` have no list to copy, so nothing is exposed then, as with
+    /// [`ExposeHeaders::None`], rather than falling back to `*`. See
+    /// [`Config::resolved_expose_headers`].
+    #[cfg_attr(feature = "serde", serde(rename = "same-as-allowed"))]
+    SameAsAllowed,
     /// Only expose a specific list of headers
     #[cfg_attr(feature = "serde", serde(untagged))]
     List(
-        #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))] HashSet<http::HeaderName>,
+        #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "HashSet<String>"))]
+        HashSet<http::HeaderName>,
     ),
 }
 
+impl ExposeHeaders {
+    /// Expose no headers, see [`ExposeHeaders::None`]
+    pub fn none() -> Self {
+        ExposeHeaders::None
+    }
+
+    /// Expose exactly these headers
+    pub fn list(headers: impl IntoIterator<Item = http::HeaderName>) -> Self {
+        ExposeHeaders::List(headers.into_iter().collect())
+    }
+
+    /// The exposed headers sorted by name, empty for `None`, or `None` for
+    /// `Any`. `SameAsAllowed` is empty too, since the allowed headers aren't
+    /// known here; resolve it first with
+    /// [`Config::resolved_expose_headers`].
+    pub fn as_sorted_vec(&self) -> Option<Vec<&http::HeaderName>> {
+        match self {
+            ExposeHeaders::Any => None,
+            ExposeHeaders::None | ExposeHeaders::SameAsAllowed => Some(Vec::new()),
+            ExposeHeaders::List(headers) => Some(sorted_headers(headers)),
+        }
+    }
+
+    /// How many headers are exposed: zero for `None` and, like
+    /// [`ExposeHeaders::as_sorted_vec`], for `SameAsAllowed`, or `None` for
+    /// `Any`
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            ExposeHeaders::Any => None,
+            ExposeHeaders::None | ExposeHeaders::SameAsAllowed => Some(0),
+            ExposeHeaders::List(headers) => Some(headers.len()),
+        }
+    }
+
+    /// Whether no headers beyond the CORS-safelisted ones are exposed
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+fn sorted_headers(headers: &HashSet<http::HeaderName>) -> Vec<&http::HeaderName> {
+    let mut headers = headers.iter().collect::<Vec<_>>();
+    headers.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+    headers
+}
+
+/// Only the CORS-safelisted response headers
+impl Default for ExposeHeaders {
+    fn default() -> Self {
+        ExposeHeaders::List(HashSet::new())
+    }
+}
+
+/// `SameAsAllowed` exposes nothing, since the allowed headers aren't known
+/// here. Converting a whole [`Config`] resolves it first.
 impl From<ExposeHeaders> for tower_http::cors::ExposeHeaders {
     fn from(value: ExposeHeaders) -> Self {
         match value {
             ExposeHeaders::Any => tower_http::cors::ExposeHeaders::any(),
+            ExposeHeaders::None | ExposeHeaders::SameAsAllowed => {
+                tower_http::cors::ExposeHeaders::default()
+            }
             ExposeHeaders::List(headers) => tower_http::cors::ExposeHeaders::list(headers),
         }
     }
 }
 
-/// A wrapper around `RegexSet` that is serializable with serde
-#[derive(Debug, Clone)]
+/// A wrapper around `RegexSet` that is serializable with serde.
+///
+/// Only the pattern strings are kept: they are what gets serialized, and
+/// matching recompiles them when anchoring. Options set on a
+/// `RegexSetBuilder`, such as `case_insensitive`, are not visible through
+/// `RegexSet::patterns` and are lost in both cases. Write them as inline
+/// flags instead, e.g. `(?i)https://example\.com`, which survive a
+/// roundtrip and behave the same everywhere.
+///
+/// The anchored copy is compiled along with the set, so a pattern that stops
+/// compiling once wrapped in `^(?:...)$`, like `(?x)a # comment`, is
+/// rejected here rather than when a layer is built. Both live behind an
+/// `Arc`, so clones share them.
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SerdeRegexSet(
-    #[cfg_attr(feature = "serde", serde(with = "serde_regex_set"))] pub RegexSet,
+    #[cfg_attr(feature = "serde", serde(with = "serde_shared_regex_set"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "HashSet<String>"))]
+    Arc<RegexSets>,
 );
 
-impl std::ops::Deref for SerdeRegexSet {
-    type Target = RegexSet;
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// A set as written, and its anchored copy
+#[derive(Debug)]
+struct RegexSets {
+    patterns: RegexSet,
+    anchored: RegexSet,
+}
+
+impl RegexSets {
+    fn new(patterns: RegexSet) -> Result<Self, regex::Error> {
+        let anchored = anchor(patterns.patterns(), strip_anchors).or_else(|_| {
+            // dropping a `^` can break a pattern, e.g. `^+a`, so keep those
+            // as written
+            anchor(patterns.patterns(), |pattern| {
+                let stripped = strip_anchors(pattern);
+                if regex::Regex::new(stripped).is_ok() {
+                    stripped
+                } else {
+                    pattern
+                }
+            })
+        })?;
+        Ok(RegexSets { patterns, anchored })
     }
 }
 
-#[cfg(feature = "serde")]
-mod serde_regex_set {
-    use regex::RegexSet;
-    use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
-    use std::collections::HashSet;
+/// Compile `patterns`, each wrapped to match the whole input after `strip`
+fn anchor<'a>(
+    patterns: &'a [String],
+    strip: impl Fn(&'a str) -> &'a str,
+) -> Result<RegexSet, regex::Error> {
+    RegexSet::new(
+        patterns
+            .iter()
+            .map(|pattern| format!("^(?:{})$", strip(pattern))),
+    )
+}
 
-    pub fn serialize<S>(value: &RegexSet, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
+impl SerdeRegexSet {
+    /// Wrap `patterns`, which are matched unanchored through `Deref` and
+    /// anchored by everything in this crate. Fails if a pattern doesn't
+    /// compile once anchored.
+    pub fn new(patterns: RegexSet) -> Result<Self, regex::Error> {
+        Ok(SerdeRegexSet(Arc::new(RegexSets::new(patterns)?)))
+    }
+
+    /// The set where every pattern must match the whole input. A leading `^`
+    /// or trailing `$` a pattern already has is dropped before wrapping
+    /// rather than doubled, unless the pattern doesn't compile without it.
+    ///
+    /// It was compiled along with this set and is shared by its clones, so
+    /// this only clones it.
+    pub fn anchored(&self) -> RegexSet {
+        self.0.anchored.clone()
+    }
+
+    /// The set as written, without the anchors matching adds
+    pub fn into_inner(self) -> RegexSet {
+        Arc::try_unwrap(self.0)
+            .map(|sets| sets.patterns)
+            .unwrap_or_else(|sets| sets.patterns.clone())
+    }
+}
+
+impl TryFrom<RegexSet> for SerdeRegexSet {
+    type Error = regex::Error;
+
+    fn try_from(patterns: RegexSet) -> Result<Self, Self::Error> {
+        SerdeRegexSet::new(patterns)
+    }
+}
+
+impl fmt::Debug for SerdeRegexSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SerdeRegexSet")
+            .field(&self.0.patterns)
+            .finish()
+    }
+}
+
+/// `pattern` without a leading `^` and an unescaped trailing `$`
+pub(crate) fn strip_anchors(pattern: &str) -> &str {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    match pattern.strip_suffix('$') {
+        // an odd number of backslashes before the `$` escapes it
+        Some(rest) if (rest.len() - rest.trim_end_matches('\\').len()).is_multiple_of(2) => rest,
+        _ => pattern,
+    }
+}
+
+/// Sets are equal when they hold the same patterns, regardless of order
+impl PartialEq for SerdeRegexSet {
+    fn eq(&self, other: &Self) -> bool {
+        fn patterns(set: &SerdeRegexSet) -> BTreeSet<&str> {
+            set.patterns().iter().map(String::as_str).collect()
+        }
+        patterns(self) == patterns(other)
+    }
+}
+
+impl Eq for SerdeRegexSet {}
+
+impl std::ops::Deref for SerdeRegexSet {
+    type Target = RegexSet;
+    fn deref(&self) -> &Self::Target {
+        &self.0.patterns
+    }
+}
+
+/// Serializes the shared sets of a [`SerdeRegexSet`] as the patterns they
+/// were compiled from
+#[cfg(feature = "serde")]
+mod serde_shared_regex_set {
+    use std::sync::Arc;
+
+    use serde::{de, Deserializer, Serializer};
+
+    use crate::{ConfigError, RegexSets};
+
+    pub fn serialize<S>(value: &Arc<RegexSets>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::serde_regex_set::serialize(&value.patterns, serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Arc<RegexSets>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RegexSets::new(super::serde_regex_set::deserialize(deserializer)?)
+            .map(Arc::new)
+            .map_err(|error| de::Error::custom(ConfigError::from(error)))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_regex_set {
+    use regex::RegexSet;
+    use serde::{de, ser::SerializeSeq, Deserializer, Serializer};
+    use std::collections::HashSet;
+
+    use crate::ConfigError;
+
+    pub fn serialize<S>(value: &RegexSet, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
     {
-        let mut sequence = serializer.serialize_seq(Some(value.len()))?;
-        for regex in value.patterns() {
+        let mut sorted: Vec<&String> = value.patterns().iter().collect();
+        sorted.sort();
+        let mut sequence = serializer.serialize_seq(Some(sorted.len()))?;
+        for regex in sorted {
             sequence.serialize_element(regex)?;
         }
         sequence.end()
@@ -188,109 +1303,2072 @@ mod serde_regex_set {
     where
         D: Deserializer<'de>,
     {
-        let values: HashSet<String> = Deserialize::deserialize(deserializer)?;
-        RegexSet::new(values).map_err(de::Error::custom)
+        let values: HashSet<String> = super::serde_one_or_many::deserialize(deserializer)?
+            .into_iter()
+            .collect();
+        RegexSet::new(values).map_err(|error| de::Error::custom(ConfigError::from(error)))
     }
 }
 
+/// The header names sent in `Vary`.
+///
+/// The list always replaces tower-http's own default (the `Origin` and
+/// `Access-Control-Request-*` request headers), and the empty default sends
+/// no `Vary` header at all, leaving it to the application or a CDN. There is
+/// no way to mirror request headers into `Vary`: tower-http only takes a
+/// fixed list.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Vary(
-    #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))] pub HashSet<http::HeaderName>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_header_name"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "HashSet<String>"))]
+    pub HashSet<http::HeaderName>,
 );
 
+impl Vary {
+    /// How many header names are sent in `Vary`
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether no `Vary` header is sent
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 impl From<Vary> for tower_http::cors::Vary {
     fn from(value: Vary) -> Self {
         tower_http::cors::Vary::list(value.0)
     }
 }
 
-#[derive(Debug, Clone)]
+/// Reads a max age as either a humantime string like `1h`, a bare number of
+/// seconds, or a `secs`/`millis` map as emitted by JS tooling, and writes it
+/// as a humantime string
+#[cfg(feature = "serde")]
+mod serde_max_age {
+    use std::{fmt, time::Duration};
+
+    use serde::{
+        de::{self, MapAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use crate::{MaxAge, MAX_BROWSER_MAX_AGE};
+
+    impl<'de> Deserialize<'de> for MaxAge {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct MaxAgeVisitor;
+
+            impl<'de> Visitor<'de> for MaxAgeVisitor {
+                type Value = MaxAge;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(
+                        "a duration like `1h`, a number of seconds, `{ secs: .. }`, \
+                         `{ millis: .. }`, `disabled` or `browser-max`",
+                    )
+                }
+
+                fn visit_u64<E: de::Error>(self, secs: u64) -> Result<MaxAge, E> {
+                    Ok(MaxAge::Seconds(Duration::from_secs(secs)))
+                }
+
+                fn visit_i64<E: de::Error>(self, secs: i64) -> Result<MaxAge, E> {
+                    u64::try_from(secs)
+                        .map(|secs| MaxAge::Seconds(Duration::from_secs(secs)))
+                        .map_err(|_| E::invalid_value(de::Unexpected::Signed(secs), &self))
+                }
+
+                fn visit_str<E: de::Error>(self, value: &str) -> Result<MaxAge, E> {
+                    match value {
+                        "disabled" => Ok(MaxAge::Disabled),
+                        "browser-max" => Ok(MaxAge::BrowserMax),
+                        _ => humantime_serde::re::humantime::parse_duration(value)
+                            .map(MaxAge::Seconds)
+                            .map_err(E::custom),
+                    }
+                }
+
+                fn visit_unit<E: de::Error>(self) -> Result<MaxAge, E> {
+                    Ok(MaxAge::Disabled)
+                }
+
+                fn visit_none<E: de::Error>(self) -> Result<MaxAge, E> {
+                    Ok(MaxAge::Disabled)
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<MaxAge, A::Error> {
+                    let max_age = match map.next_key::<String>()?.as_deref() {
+                        Some("secs") => Duration::from_secs(map.next_value()?),
+                        Some("millis") => Duration::from_millis(map.next_value()?),
+                        Some(key) => {
+                            return Err(de::Error::unknown_field(key, &["secs", "millis"]))
+                        }
+                        None => return Err(de::Error::invalid_length(0, &self)),
+                    };
+                    if let Some(key) = map.next_key::<String>()? {
+                        return Err(de::Error::custom(format_args!(
+                            "unexpected `{key}`, expected a single `secs` or `millis` key"
+                        )));
+                    }
+                    Ok(MaxAge::Seconds(max_age))
+                }
+            }
+
+            deserializer.deserialize_any(MaxAgeVisitor)
+        }
+    }
+
+    impl Serialize for MaxAge {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                MaxAge::Disabled => serializer.serialize_str("disabled"),
+                MaxAge::Seconds(max_age) => humantime_serde::serialize(max_age, serializer),
+                MaxAge::BrowserMax => serializer.serialize_str("browser-max"),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        humantime_serde::serialize(value, serializer)
+    }
+
+    /// A [`MaxAge`] that sends a header, so `browser-max` is accepted but
+    /// `disabled` isn't
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match MaxAge::deserialize(deserializer)? {
+            MaxAge::Seconds(max_age) => Ok(max_age),
+            MaxAge::BrowserMax => Ok(MAX_BROWSER_MAX_AGE),
+            MaxAge::Disabled => Err(de::Error::custom(
+                "expected a duration, a group's max age can't be disabled",
+            )),
+        }
+    }
+}
+
+/// A `max_age` that applies only to preflights from some origins
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct OriginMaxAge {
+    /// Which origins this max age applies to
+    pub origins: AllowedOrigins,
+    /// How long matching origins may cache the preflight response
+    #[cfg_attr(feature = "serde", serde(with = "serde_max_age"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub max_age: Duration,
+}
+
+/// CORS settings that can be turned into a [`CorsLayer`].
+///
+/// The [`Default`] is deliberately restrictive: no origins, only `GET` and
+/// `HEAD`, no extra headers and no credentials. A field that is forgotten
+/// fails safe rather than opening anything up. The same goes for config
+/// files: every field may be left out and falls back to its default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Config {
+    /// Whether to add CORS handling at all. Defaults to true, and is omitted
+    /// when serializing unless false.
+    ///
+    /// Turning it off, e.g. behind a feature flag, makes
+    /// [`Config::into_optional_layer`] return `None` so no layer is added,
+    /// which is different from a deny-all config that rejects cross-origin
+    /// requests. `CorsLayer::try_from` ignores this field.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "enabled_by_default", skip_serializing_if = "is_true")
+    )]
+    pub enabled: bool,
+    /// The format version the config was written for, [`CONFIG_VERSION`]
+    /// unless set. Unknown versions, e.g. from a newer release of this
+    /// crate, are rejected when deserializing rather than misread; see
+    /// [`Config::migrate`]. Omitted when serializing if current.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default = "version::current",
+            deserialize_with = "version::deserialize",
+            skip_serializing_if = "version::is_current"
+        )
+    )]
+    pub version: u32,
     /// Whether to allow credentials in CORS requests
     #[cfg_attr(feature = "serde", serde(default))]
     pub allow_credentials: bool,
     /// Which request headers can be sent in the actual request.
     /// Controls the [`Access-Control-Allow-Headers`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Headers) response header.
+    /// Defaults to no headers beyond the CORS-safelisted ones.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub allowed_headers: AllowedHeaders,
     /// Controls how to set the [`Access-Control-Allow-Methods`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Methods) response header.
+    /// Defaults to `GET` and `HEAD`.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub allowed_methods: AllowedMethods,
     /// Controls how to set the [`Access-Control-Allow-Origin`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Origin) response header.
+    /// Defaults to no origins.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub allowed_origins: AllowedOrigins,
+    /// Origins that are always rejected, even if they match `allowed_origins`.
+    ///
+    /// Patterns are anchored the same way as [`AllowedOrigins::List`]. When
+    /// set together with `allowed_origins: any`, the request origin is
+    /// mirrored back instead of responding with `*`, since a wildcard can't
+    /// exclude anything.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub denied_origins: Option<SerdeRegexSet>,
     /// If true, include the [`Access-Control-Allow-Private-Network`](https://wicg.github.io/private-network-access/) response header.
+    ///
+    /// This lets public websites reach a server on the user's private
+    /// network, so only enable it together with a list of trusted
+    /// `allowed_origins`. With `any` or `mirror` origins, any site on the
+    /// internet could, and [`Config::warnings`] says so.
     #[cfg_attr(feature = "serde", serde(default))]
     pub allow_private_network: bool,
-    /// The maximum age of the CORS request in seconds
+    /// How long browsers may cache the preflight response, written either as
+    /// a humantime string like `1h`, as a number of seconds, or as
+    /// `{ secs: 3600 }` or `{ millis: 3600000 }`. It is always serialized as
+    /// a humantime string. Browsers only see whole seconds, so milliseconds
+    /// are rounded down when sent.
+    ///
+    /// Zero is sent as `Access-Control-Max-Age: 0`, which tells browsers not
+    /// to cache preflights at all. Leaving it unset (or `disabled`) omits the
+    /// header, so browsers fall back to their own default (5 seconds), and
+    /// `browser-max` sends [`MAX_BROWSER_MAX_AGE`], see [`MaxAge`].
     #[cfg_attr(
         feature = "serde",
-        serde(
-            with = "humantime_serde",
-            default,
-            skip_serializing_if = "Option::is_none"
-        )
+        serde(default, skip_serializing_if = "MaxAge::is_disabled")
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub max_age: MaxAge,
+    /// Different `max_age` values for groups of origins. The first group
+    /// whose origins match the preflight's `Origin` is used, falling back to
+    /// `max_age`, or to `0` if that is unset too.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
     )]
-    pub max_age: Option<Duration>,
+    pub max_age_by_origin: Vec<OriginMaxAge>,
     /// Which headers are exposed to the client.
     /// Controls the [`Access-Control-Expose-Headers`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Expose-Headers) response header.
-    pub expose_headers: ExposeHeaders,
-    /// Which headers to set in the Vary response header
+    /// Defaults to only the CORS-safelisted response headers.
     #[cfg_attr(feature = "serde", serde(default))]
+    pub expose_headers: ExposeHeaders,
+    /// Which headers to set in the Vary response header. Omitted when
+    /// serializing if empty.
+    ///
+    /// This is the whole list: tower-http adds nothing of its own, not even
+    /// `Origin` for mirrored or matched origins, so include it whenever the
+    /// response depends on the origin. Names are case-insensitive and each
+    /// is sent once.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vary::is_empty")
+    )]
     pub vary: Vary,
+    /// Called with every origin the layer checks and whether it was allowed.
+    /// Not part of the serialized config.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_origin_decision: Option<OriginDecisionHook>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: false,
+            allowed_headers: AllowedHeaders::default(),
+            allowed_methods: AllowedMethods::default(),
+            allowed_origins: AllowedOrigins::default(),
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::default(),
+            vary: Vary::default(),
+            on_origin_decision: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[cfg(feature = "serde")]
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// A callback told about each origin decision, e.g. to count rejected
+/// cross-origin requests.
+///
+/// The origin is passed as a string, lossily converted if it isn't valid
+/// UTF-8. With `allowed-origins: any` and no `denied-origins`, tower-http
+/// answers `*` without looking at the origin, so the hook is never called.
+///
+/// Hooks compare equal (and hash the same) only if they are clones of the
+/// same `Arc`.
+#[derive(Clone)]
+pub struct OriginDecisionHook(Arc<OriginDecisionFn>);
+
+type OriginDecisionFn = dyn Fn(&str, bool) + Send + Sync;
+
+impl OriginDecisionHook {
+    pub fn new(hook: impl Fn(&str, bool) + Send + Sync + 'static) -> Self {
+        OriginDecisionHook(Arc::new(hook))
+    }
+
+    fn call(&self, origin: &http::HeaderValue, allowed: bool) {
+        (self.0)(&String::from_utf8_lossy(origin.as_bytes()), allowed)
+    }
 }
 
-impl From<Config> for CorsLayer {
-    fn from(config: Config) -> Self {
-        let mut layer = CorsLayer::new()
-            .allow_credentials(config.allow_credentials)
-            .allow_headers(config.allowed_headers)
-            .allow_methods(config.allowed_methods)
-            .allow_origin(config.allowed_origins)
-            .allow_private_network(config.allow_private_network)
-            .expose_headers(config.expose_headers)
-            .vary(config.vary);
+impl From<Arc<dyn Fn(&str, bool) + Send + Sync>> for OriginDecisionHook {
+    fn from(hook: Arc<dyn Fn(&str, bool) + Send + Sync>) -> Self {
+        OriginDecisionHook(hook)
+    }
+}
+
+impl fmt::Debug for OriginDecisionHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OriginDecisionHook").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for OriginDecisionHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for OriginDecisionHook {}
+
+impl std::hash::Hash for OriginDecisionHook {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// Builds the layer, rejecting configs that tower-http would otherwise panic
+/// on the first time the service is used.
+///
+/// Origin pattern lists are compiled, anchored too, when their
+/// [`SerdeRegexSet`] is built or deserialized, which is the expensive part:
+/// for 20 patterns that makes about 3,100 allocations (900 KiB). Converting
+/// the config, or any clone of it, reuses them and makes about 10 (2 KiB),
+/// and cloning the finished `CorsLayer` makes one. `tests/allocations.rs`
+/// measures these.
+///
+/// [`AllowedHeaders::MirrorCapped`] and [`AllowedHeaders::Regex`] need a
+/// [`ConfigLayer`] instead.
+impl TryFrom<Config> for CorsLayer {
+    type Error = ConfigError;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        if matches!(
+            config.allowed_headers,
+            AllowedHeaders::MirrorCapped(_) | AllowedHeaders::Regex(_)
+        ) {
+            return Err(ConfigError::HeadersNeedConfigLayer);
+        }
+        build_layer(config)
+    }
+}
+
+/// The tower-http part of the layer, with `MirrorCapped` and `Regex` headers
+/// allowing none
+pub(crate) fn build_layer(config: Config) -> Result<CorsLayer, ConfigError> {
+    if let Some(error) = config.errors().into_iter().next() {
+        return Err(error);
+    }
+
+    let expose_headers = config.resolved_expose_headers();
+    let mut layer = CorsLayer::new()
+        .allow_credentials(config.allow_credentials)
+        .allow_headers(config.allowed_headers)
+        .allow_methods(config.allowed_methods)
+        .allow_origin(allow_origin(
+            config.allowed_origins,
+            config.denied_origins,
+            config.on_origin_decision,
+        ))
+        .allow_private_network(config.allow_private_network)
+        .expose_headers(expose_headers)
+        .vary(config.vary);
+
+    if !config.max_age_by_origin.is_empty() {
+        let default = config.max_age.as_duration().unwrap_or_default();
+        let groups = config
+            .max_age_by_origin
+            .into_iter()
+            .map(|group| (OriginMatcher::from(group.origins), group.max_age))
+            .collect::<Vec<_>>();
+        layer = layer.max_age(tower_http::cors::MaxAge::dynamic(move |origin, parts| {
+            groups
+                .iter()
+                .find(|(origins, _)| origins.is_match_request(origin, parts))
+                .map_or(default, |(_, max_age)| *max_age)
+        }));
+    } else if let Some(max_age) = config.max_age.as_duration() {
+        layer = layer.max_age(max_age);
+    }
+
+    Ok(layer)
+}
+
+/// Builds the layer unless it would make no difference: `None` if the config
+/// is [disabled](Config::enabled) or [a no-op](Config::is_noop).
+///
+/// Like [`Config::into_optional_layer`], the config is validated either way.
+impl TryFrom<Config> for Option<CorsLayer> {
+    type Error = ConfigError;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let noop = config.is_noop();
+        Ok(config.into_optional_layer()?.filter(|_| !noop))
+    }
+}
+
+impl Config {
+    /// Build the layer and pass it through `customize`, for tower-http
+    /// options this crate doesn't model.
+    ///
+    /// Since building can fail, this returns a `Result` just like
+    /// `CorsLayer::try_from`. Anything `customize` sets overrides the config.
+    pub fn into_layer_with(
+        self,
+        customize: impl FnOnce(CorsLayer) -> CorsLayer,
+    ) -> Result<CorsLayer, ConfigError> {
+        CorsLayer::try_from(self).map(customize)
+    }
+
+    /// Build the layer, or `None` if [`Config::enabled`] is false so the
+    /// caller can leave it out:
+    ///
+    /// ```
+    /// use axum::Router;
+    /// use tower_http_cors_config::Config;
+    ///
+    /// let config = Config {
+    ///     enabled: false,
+    ///     ..Config::default()
+    /// };
+    /// let mut app = Router::<()>::new();
+    /// if let Some(cors) = config.into_optional_layer().unwrap() {
+    ///     app = app.layer(cors);
+    /// }
+    /// # let _ = app;
+    /// ```
+    ///
+    /// A disabled config is still validated, so a broken config is caught
+    /// before the flag is turned back on.
+    pub fn into_optional_layer(self) -> Result<Option<CorsLayer>, ConfigError> {
+        let enabled = self.enabled;
+        let layer = CorsLayer::try_from(self)?;
+        Ok(enabled.then_some(layer))
+    }
+
+    /// Whether the layer would never send a CORS header, so leaving it out
+    /// changes nothing browsers see. That is exactly when
+    /// [`Config::response_headers`] is empty:
+    ///
+    /// - `allowed_origins` is an empty `Exact`, `Wildcard` or `List`. `Any`,
+    ///   `Mirror`, `LocalhostAny`, `Dynamic` and `Shared` never count as
+    ///   empty.
+    /// - `allowed_methods` and `allowed_headers` are empty lists, and nothing
+    ///   is exposed.
+    /// - credentials and private network access are off, there is no max
+    ///   age, and `vary` is empty.
+    ///
+    /// [`Config::deny_all`] is a no-op. [`Config::default`] rejects every
+    /// origin too, but still answers preflights with
+    /// `Access-Control-Allow-Methods: GET,HEAD`, so it isn't. Without a layer,
+    /// preflights reach the application instead of being answered by the
+    /// layer; browsers reject them either way.
+    pub fn is_noop(&self) -> bool {
+        self.response_headers().is_empty()
+    }
+
+    /// Add `OPTIONS` to an explicit [`AllowedMethods::List`]; other policies
+    /// already cover it.
+    ///
+    /// Preflights don't need this: the layer answers them itself, whatever
+    /// the list says. It only matters when browsers send *actual* `OPTIONS`
+    /// requests, e.g. `fetch(url, { method: "OPTIONS" })` to an API that
+    /// routes `OPTIONS` to its own handlers. `OPTIONS` isn't a CORS-safelisted
+    /// method, so that request is preflighted and fails unless it is listed.
+    pub fn ensure_options_allowed(&mut self) {
+        if let AllowedMethods::List(methods) = &mut self.allowed_methods {
+            methods.insert(http::Method::OPTIONS);
+        }
+    }
 
-        if let Some(max_age) = config.max_age {
-            layer = layer.max_age(max_age);
+    /// The exposed headers the layer sends, with
+    /// [`ExposeHeaders::SameAsAllowed`] replaced by a copy of the allowed
+    /// header list, or by `None` if headers are allowed with `Any`, `Mirror`
+    /// or `Regex`
+    pub fn resolved_expose_headers(&self) -> ExposeHeaders {
+        match (&self.expose_headers, &self.allowed_headers) {
+            (
+                ExposeHeaders::SameAsAllowed,
+                AllowedHeaders::List(headers) | AllowedHeaders::MirrorList(headers),
+            ) => ExposeHeaders::List(headers.clone()),
+            (
+                ExposeHeaders::SameAsAllowed,
+                AllowedHeaders::Any
+                | AllowedHeaders::Mirror
+                | AllowedHeaders::MirrorCapped(_)
+                | AllowedHeaders::Regex(_),
+            ) => ExposeHeaders::None,
+            (expose_headers, _) => expose_headers.clone(),
         }
+    }
+}
 
-        layer
+/// Combine the allow- and denylists, reporting each decision to `hook`
+fn allow_origin(
+    allowed: AllowedOrigins,
+    denied: Option<SerdeRegexSet>,
+    hook: Option<OriginDecisionHook>,
+) -> AllowOrigin {
+    match (allowed, denied, hook) {
+        (allowed, None, None) | (allowed @ AllowedOrigins::Any, None, Some(_)) => allowed.into(),
+        (allowed, denied, hook) => {
+            // a denylist can't be expressed with `*`, so `any` mirrors instead
+            let denied = denied.map(|denied| OriginMatcher::from(AllowedOrigins::List(denied)));
+            let allowed = OriginMatcher::from(allowed);
+            AllowOrigin::predicate(move |origin, parts| {
+                let is_denied = denied
+                    .as_ref()
+                    .is_some_and(|denied| denied.is_match(origin));
+                let is_allowed = !is_denied && allowed.is_match_request(origin, parts);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    ?origin,
+                    allowed = is_allowed,
+                    denied = is_denied,
+                    "evaluated CORS origin"
+                );
+                if let Some(hook) = &hook {
+                    hook.call(origin, is_allowed);
+                }
+                is_allowed
+            })
+        }
     }
 }
 
-#[cfg(all(feature = "serde", test))]
+#[cfg(test)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    /// A service that always responds with `200 OK`
+    pub(crate) struct AlwaysOk;
+
+    impl tower_service::Service<http::Request<()>> for AlwaysOk {
+        type Response = http::Response<()>;
+        type Error = std::convert::Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Result::Ok(()))
+        }
+
+        fn call(&mut self, _request: http::Request<()>) -> Self::Future {
+            std::future::ready(Result::Ok(http::Response::new(())))
+        }
+    }
+
+    /// Run `request` through `layer` in front of [`AlwaysOk`], returning the
+    /// response headers
+    pub(crate) fn cors_headers<L>(layer: L, request: http::Request<()>) -> http::HeaderMap
+    where
+        L: tower_layer::Layer<AlwaysOk>,
+        L::Service: tower_service::Service<
+            http::Request<()>,
+            Response = http::Response<()>,
+            Error = std::convert::Infallible,
+        >,
+    {
+        use std::{
+            future::Future,
+            task::{Context, Poll, Waker},
+        };
+        use tower_service::Service;
+
+        let mut service = layer.layer(AlwaysOk);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(service.poll_ready(&mut cx).is_ready());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Result::Ok(response)) => response.headers().clone(),
+            Poll::Ready(Err(error)) => match error {},
+            Poll::Pending => unreachable!("the inner service is always ready"),
+        }
+    }
+
+    /// A preflight request from `origin` asking to send a `GET`
+    pub(crate) fn preflight_from(origin: &str) -> http::Request<()> {
+        http::Request::options("/")
+            .header(http::header::ORIGIN, origin)
+            .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(())
+            .unwrap()
+    }
+
+    /// A simple cross-origin `GET` request from `origin`
+    pub(crate) fn request_from(origin: &str) -> http::Request<()> {
+        http::Request::get("/")
+            .header(http::header::ORIGIN, origin)
+            .body(())
+            .unwrap()
+    }
+
     #[test]
-    fn test_roundtrip() {
-        let config = Config {
-            allow_credentials: true,
-            allowed_headers: AllowedHeaders::List(HashSet::from([
-                http::header::CONNECTION,
-                http::header::AUTHORIZATION,
+    fn test_none_constructors() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_headers: AllowedHeaders::none(),
+            allowed_methods: AllowedMethods::none(),
+            allowed_origins: AllowedOrigins::exact("https://example.com"),
+            expose_headers: ExposeHeaders::none(),
+            ..Config::default()
+        })
+        .unwrap();
+        let headers = cors_headers(layer.clone(), preflight_from("https://example.com"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_HEADERS));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_METHODS));
+        let headers = cors_headers(layer, request_from("https://example.com"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_EXPOSE_HEADERS));
+
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::none(),
+            ..Config::default()
+        })
+        .unwrap();
+        let headers = cors_headers(layer, request_from("https://example.com"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_try_from_str() {
+        use http::{header, Method};
+
+        assert_eq!(AllowedMethods::try_from("*").unwrap(), AllowedMethods::Any);
+        assert_eq!(
+            AllowedMethods::try_from("any").unwrap(),
+            AllowedMethods::Any
+        );
+        assert_eq!(
+            AllowedMethods::try_from("mirror").unwrap(),
+            AllowedMethods::Mirror
+        );
+        assert_eq!(
+            AllowedMethods::try_from("GET, post").unwrap(),
+            AllowedMethods::list([Method::GET, Method::POST])
+        );
+        assert!(matches!(
+            AllowedMethods::try_from("GET, P OST"),
+            Err(ConfigError::InvalidMethod(_))
+        ));
+
+        assert_eq!(AllowedHeaders::try_from("*").unwrap(), AllowedHeaders::Any);
+        assert_eq!(
+            AllowedHeaders::try_from(" mirror ").unwrap(),
+            AllowedHeaders::Mirror
+        );
+        assert_eq!(
+            AllowedHeaders::try_from("Content-Type,authorization,").unwrap(),
+            AllowedHeaders::list([header::CONTENT_TYPE, header::AUTHORIZATION])
+        );
+        assert_eq!(
+            AllowedHeaders::try_from("").unwrap(),
+            AllowedHeaders::none()
+        );
+        assert!(matches!(
+            AllowedHeaders::try_from("x header"),
+            Err(ConfigError::InvalidHeaderName(_))
+        ));
+    }
+
+    #[test]
+    fn test_ensure_options_allowed() {
+        let mut config = Config::default();
+        config.ensure_options_allowed();
+        assert_eq!(
+            config.allowed_methods,
+            AllowedMethods::list([http::Method::GET, http::Method::HEAD, http::Method::OPTIONS])
+        );
+
+        let mut config = Config::permissive();
+        config.ensure_options_allowed();
+        assert_eq!(config.allowed_methods, AllowedMethods::Any);
+    }
+
+    #[test]
+    fn test_empty_vary_sends_nothing() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Mirror,
+            ..Config::default()
+        })
+        .unwrap();
+        for request in [
+            preflight_from("https://example.com"),
+            request_from("https://example.com"),
+        ] {
+            let headers = cors_headers(layer.clone(), request);
+            assert!(!headers.contains_key(http::header::VARY));
+        }
+    }
+
+    #[test]
+    fn test_vary_origin_is_sent_once() {
+        // mirroring makes tower-http vary on the origin too
+        for vary in [
+            Vary(HashSet::from([http::header::ORIGIN])),
+            Vary(HashSet::from([
+                http::header::ORIGIN,
+                http::header::ACCESS_CONTROL_REQUEST_METHOD,
             ])),
-            allowed_methods: AllowedMethods::Mirror,
-            allowed_origins: AllowedOrigins::Any,
-            allow_private_network: true,
-            max_age: Some(Duration::from_secs(3600)),
-            expose_headers: ExposeHeaders::Any,
-            vary: Vary(HashSet::from([http::HeaderName::from_static("origin")])),
-        };
-        let serialized = serde_yaml::to_string(&config).unwrap();
-        let deserialized: Config = serde_yaml::from_str(&serialized).unwrap();
-        // regex::RegexSet does not implement PartialEq
-        assert_eq!(config.allow_credentials, deserialized.allow_credentials);
-        assert_eq!(config.allowed_headers, deserialized.allowed_headers);
-        assert_eq!(config.allowed_methods, deserialized.allowed_methods);
+        ] {
+            let layer = CorsLayer::try_from(Config {
+                allowed_origins: AllowedOrigins::Mirror,
+                vary: vary.clone(),
+                ..Config::default()
+            })
+            .unwrap();
+            for request in [
+                preflight_from("https://example.com"),
+                request_from("https://example.com"),
+            ] {
+                let headers = cors_headers(layer.clone(), request);
+                let origins = headers
+                    .get_all(http::header::VARY)
+                    .iter()
+                    .flat_map(|value| value.to_str().unwrap().split(','))
+                    .filter(|name| name.trim().eq_ignore_ascii_case("origin"))
+                    .count();
+                assert_eq!(origins, 1, "{vary:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_sorted_vec() {
+        use http::{header, Method};
+
+        assert_eq!(
+            AllowedMethods::list([Method::PUT, Method::GET, Method::DELETE]).as_sorted_vec(),
+            Some(vec![&Method::DELETE, &Method::GET, &Method::PUT])
+        );
+        assert_eq!(AllowedMethods::Mirror.as_sorted_vec(), None);
+        assert_eq!(
+            AllowedHeaders::MirrorList(HashSet::from([header::RANGE, header::ACCEPT]))
+                .as_sorted_vec(),
+            Some(vec![&header::ACCEPT, &header::RANGE])
+        );
+        assert_eq!(AllowedHeaders::Any.as_sorted_vec(), None);
+        assert_eq!(ExposeHeaders::none().as_sorted_vec(), Some(vec![]));
+        assert_eq!(ExposeHeaders::Any.as_sorted_vec(), None);
+    }
+
+    #[test]
+    fn test_len() {
+        use http::{header, Method};
+
+        assert_eq!(AllowedOrigins::exact("https://a.example").len(), Some(1));
+        assert_eq!(
+            AllowedOrigins::host_only(&["a.example", "b.example"]).len(),
+            Some(2)
+        );
+        assert!(AllowedOrigins::none().is_empty());
+        assert_eq!(AllowedOrigins::Mirror.len(), None);
+        assert!(!AllowedOrigins::Any.is_empty());
+        assert_eq!(
+            AllowedHeaders::MirrorList(HashSet::from([header::RANGE])).len(),
+            Some(1)
+        );
+        assert_eq!(AllowedHeaders::Any.len(), None);
+        assert!(AllowedHeaders::none().is_empty());
+        assert_eq!(AllowedMethods::list([Method::GET]).len(), Some(1));
+        assert_eq!(AllowedMethods::Mirror.len(), None);
+        assert!(ExposeHeaders::none().is_empty());
+        assert!(ExposeHeaders::SameAsAllowed.is_empty());
+        assert_eq!(ExposeHeaders::Any.len(), None);
+        assert_eq!(Vary(HashSet::from([header::ORIGIN])).len(), 1);
+        assert!(Vary::default().is_empty());
+    }
+
+    #[test]
+    fn test_pre_anchored_patterns() {
         assert_eq!(
-            config.allow_private_network,
-            deserialized.allow_private_network
+            strip_anchors(r"^https://a\.example$"),
+            r"https://a\.example"
         );
-        assert_eq!(config.max_age, deserialized.max_age);
-        assert_eq!(config.expose_headers, deserialized.expose_headers);
-        assert_eq!(config.vary, deserialized.vary);
+        assert_eq!(strip_anchors(r"https://a\.example"), r"https://a\.example");
+        assert_eq!(
+            strip_anchors(r"https://a\.example\$"),
+            r"https://a\.example\$"
+        );
+        assert_eq!(strip_anchors(r"https://a\\$"), r"https://a\\");
+        assert_eq!(strip_anchors("^a|b$"), "a|b");
+
+        for pattern in [r"https://.*\.example\.com", r"^https://.*\.example\.com$"] {
+            let matcher = AllowedOrigins::regex(pattern).unwrap().matcher();
+            assert!(matcher.is_match_str("https://api.example.com"), "{pattern}");
+            assert!(
+                !matcher.is_match_str("https://api.example.com.evil"),
+                "{pattern}"
+            );
+            assert!(
+                !matcher.is_match_str("http://x/https://api.example.com"),
+                "{pattern}"
+            );
+        }
+        // only the whole pattern is anchored, not each alternative
+        let matcher = AllowedOrigins::regex(r"^https://a\.example|https://b\.example$")
+            .unwrap()
+            .matcher();
+        assert!(matcher.is_match_str("https://a.example"));
+        assert!(!matcher.is_match_str("https://a.example.evil"));
+
+        // a repeated `^` can't be dropped, so these stay as written
+        for pattern in [
+            r"^+https://a\.example",
+            r"^*https://a\.example",
+            r"^?https://a\.example",
+            r"^{2}https://a\.example",
+        ] {
+            let origins = AllowedOrigins::regex(pattern).unwrap();
+            assert!(
+                origins.matcher().is_match_str("https://a.example"),
+                "{pattern}"
+            );
+            assert!(
+                !origins.matcher().is_match_str("https://a.example.evil"),
+                "{pattern}"
+            );
+            assert!(CorsLayer::try_from(Config {
+                allowed_origins: origins,
+                ..Config::default()
+            })
+            .is_ok());
+        }
+        #[cfg(feature = "serde")]
+        {
+            let config: Config =
+                serde_yaml::from_str(r"allowed-origins: ['^+https://a\.example']").unwrap();
+            assert!(CorsLayer::try_from(config).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_list_constructors() {
+        use http::{header, Method};
+
+        assert_eq!(
+            AllowedMethods::list([Method::GET, Method::POST, Method::GET]),
+            AllowedMethods::List(HashSet::from([Method::GET, Method::POST]))
+        );
+        assert_eq!(
+            AllowedHeaders::list(vec![header::CONTENT_TYPE]),
+            AllowedHeaders::List(HashSet::from([header::CONTENT_TYPE]))
+        );
+        assert_eq!(
+            ExposeHeaders::list([header::ETAG]),
+            ExposeHeaders::List(HashSet::from([header::ETAG]))
+        );
+    }
+
+    #[test]
+    fn test_exact_origins() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Exact(HashSet::from([
+                "https://example.com".to_string()
+            ])),
+            ..credentialed_config()
+        })
+        .unwrap();
+        let allowed = |origin| {
+            cors_headers(layer.clone(), request_from(origin))
+                .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_some_and(|allowed| allowed == origin)
+        };
+        assert!(allowed("https://example.com"));
+        assert!(!allowed("https://exampleXcom"));
+        assert!(!allowed("https://example.com.evil.com"));
+        assert!(!allowed("https://EXAMPLE.com"));
+    }
+
+    #[test]
+    fn test_wildcard_origins() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Wildcard(HashSet::from([
+                "https://*.example.com".to_string()
+            ])),
+            ..credentialed_config()
+        })
+        .unwrap();
+        let allowed = |origin| {
+            cors_headers(layer.clone(), request_from(origin))
+                .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_some_and(|allowed| allowed == origin)
+        };
+        assert!(allowed("https://api.example.com"));
+        assert!(allowed("https://my-app.example.com"));
+        assert!(!allowed("https://example.com"));
+        assert!(!allowed("https://evil.com"));
+        assert!(!allowed("https://a.b.example.com"));
+        assert!(!allowed("https://api.exampleXcom"));
+        assert!(!allowed("https://api.example.com.evil.com"));
+        assert!(!allowed("http://api.example.com"));
+    }
+
+    #[test]
+    fn test_default_config_denies_everything() {
+        let config = Config::default();
+        assert!(!config.allow_credentials);
+        assert_eq!(
+            config.allowed_methods,
+            AllowedMethods::List(HashSet::from([http::Method::GET, http::Method::HEAD]))
+        );
+        let layer = CorsLayer::try_from(config).unwrap();
+        for origin in ["https://example.com", "null"] {
+            let headers = cors_headers(layer.clone(), preflight_from(origin));
+            assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        }
+    }
+
+    #[test]
+    fn test_origin_decision_hook() {
+        use std::sync::Mutex;
+
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let config = Config::builder()
+            .allow_origin(AllowedOrigins::exact("https://example.com"))
+            .allow_method(http::Method::GET)
+            .on_origin_decision({
+                let decisions = decisions.clone();
+                move |origin, allowed| decisions.lock().unwrap().push((origin.to_owned(), allowed))
+            })
+            .build()
+            .unwrap();
+        assert_eq!(config.clone(), config);
+        let layer = CorsLayer::try_from(config).unwrap();
+        cors_headers(layer.clone(), request_from("https://example.com"));
+        cors_headers(layer, preflight_from("https://evil.com"));
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [
+                ("https://example.com".to_owned(), true),
+                ("https://evil.com".to_owned(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expose_headers_none() {
+        let layer = CorsLayer::try_from(Config {
+            expose_headers: ExposeHeaders::none(),
+            ..Config::permissive()
+        })
+        .unwrap();
+        let headers = cors_headers(layer, request_from("https://example.com"));
+        assert!(headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_EXPOSE_HEADERS));
+    }
+
+    #[test]
+    fn test_dynamic_origins_see_the_request() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Dynamic(OriginPredicate::new(|origin, parts| {
+                origin == "https://example.com" && parts.uri.path().starts_with("/public/")
+            })),
+            ..credentialed_config()
+        })
+        .unwrap();
+        let allowed = |path| {
+            let mut request = request_from("https://example.com");
+            *request.uri_mut() = http::Uri::from_static(path);
+            cors_headers(layer.clone(), request)
+                .contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        };
+        assert!(allowed("/public/index.html"));
+        assert!(!allowed("/admin"));
+    }
+
+    #[test]
+    fn test_shared_origins_can_be_replaced() {
+        let origins = SharedOrigins::new(RegexSet::new([r"https://a\.example"]).unwrap()).unwrap();
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Shared(origins.clone()),
+            ..credentialed_config()
+        })
+        .unwrap();
+        let allowed = |origin| {
+            cors_headers(layer.clone(), request_from(origin))
+                .contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        };
+        assert!(allowed("https://a.example"));
+        assert!(!allowed("https://b.example"));
+
+        origins
+            .replace(RegexSet::new([r"https://b\.example"]).unwrap())
+            .unwrap();
+        assert!(!allowed("https://a.example"));
+        assert!(allowed("https://b.example"));
+    }
+
+    #[test]
+    fn test_into_layer_with() {
+        let layer = credentialed_config()
+            .into_layer_with(|layer| layer.allow_private_network(true))
+            .unwrap();
+        let mut preflight = preflight_from("https://example.com");
+        preflight.headers_mut().insert(
+            "access-control-request-private-network",
+            http::HeaderValue::from_static("true"),
+        );
+        assert_eq!(
+            cors_headers(layer, preflight)["access-control-allow-private-network"],
+            "true"
+        );
+
+        let config = Config {
+            allowed_origins: AllowedOrigins::Any,
+            ..credentialed_config()
+        };
+        assert!(config.into_layer_with(|layer| layer).is_err());
+    }
+
+    #[test]
+    fn test_into_optional_layer() {
+        let layer = credentialed_config()
+            .into_optional_layer()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            cors_headers(layer, request_from("https://example.com"))
+                [http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+
+        let disabled = Config {
+            enabled: false,
+            ..credentialed_config()
+        };
+        assert!(disabled.clone().into_optional_layer().unwrap().is_none());
+        // the field only matters here
+        assert!(CorsLayer::try_from(disabled).is_ok());
+        // broken configs fail even while disabled
+        let config = Config {
+            enabled: false,
+            allowed_origins: AllowedOrigins::Any,
+            ..credentialed_config()
+        };
+        assert!(config.into_optional_layer().is_err());
+    }
+
+    #[test]
+    fn test_noop_configs_have_no_layer() {
+        assert!(Config::deny_all().is_noop());
+        assert!(Option::<CorsLayer>::try_from(Config::deny_all())
+            .unwrap()
+            .is_none());
+
+        // rejects every origin, but still answers preflights
+        let config = Config::default();
+        assert!(!config.is_noop());
+        let layer = Option::<CorsLayer>::try_from(config).unwrap().unwrap();
+        let headers = cors_headers(layer, preflight_from("https://example.com"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert!(headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_METHODS));
+
+        assert!(!Config {
+            vary: Vary(HashSet::from([http::header::ORIGIN])),
+            ..Config::deny_all()
+        }
+        .is_noop());
+        assert!(Option::<CorsLayer>::try_from(Config {
+            enabled: false,
+            ..Config::permissive()
+        })
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn test_mirror_origins_with_credentials() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::Mirror,
+            ..credentialed_config()
+        })
+        .unwrap();
+        for origin in ["https://example.com", "https://elsewhere.example"] {
+            let headers = cors_headers(layer.clone(), request_from(origin));
+            assert_eq!(headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+            assert_eq!(
+                headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+                "true"
+            );
+        }
+    }
+
+    #[test]
+    fn test_origin_lists_mirror_with_credentials() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://(app|admin)\.example\.com"]).unwrap())
+                    .unwrap(),
+            ),
+            ..credentialed_config()
+        })
+        .unwrap();
+        for origin in ["https://app.example.com", "https://admin.example.com"] {
+            let headers = cors_headers(layer.clone(), request_from(origin));
+            assert_eq!(headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+            assert_eq!(
+                headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+                "true"
+            );
+        }
+        let headers = cors_headers(layer, request_from("https://evil.example.com"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_zero_max_age_is_sent() {
+        let max_age = |max_age| {
+            let layer = CorsLayer::try_from(Config {
+                max_age,
+                ..credentialed_config()
+            })
+            .unwrap();
+            cors_headers(layer, preflight_from("https://example.com"))
+                .get(http::header::ACCESS_CONTROL_MAX_AGE)
+                .cloned()
+        };
+        assert_eq!(max_age(MaxAge::Seconds(Duration::ZERO)).unwrap(), "0");
+        assert_eq!(max_age(MaxAge::Disabled), None);
+        assert_eq!(max_age(MaxAge::BrowserMax).unwrap(), "86400");
+    }
+
+    #[test]
+    fn test_denied_origins_win() {
+        let config = Config {
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://.*\.example\.com"]).unwrap()).unwrap(),
+            ),
+            denied_origins: Some(
+                SerdeRegexSet::new(RegexSet::new([r"https://evil\.example\.com"]).unwrap())
+                    .unwrap(),
+            ),
+            ..credentialed_config()
+        };
+        let layer = CorsLayer::try_from(config.clone()).unwrap();
+        let allowed = |layer: &CorsLayer, origin| {
+            cors_headers(layer.clone(), request_from(origin))
+                .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_some_and(|allowed| allowed == origin)
+        };
+        assert!(allowed(&layer, "https://api.example.com"));
+        assert!(!allowed(&layer, "https://evil.example.com"));
+        assert!(!allowed(&layer, "https://other.com"));
+
+        let layer = CorsLayer::try_from(Config {
+            allow_credentials: false,
+            allowed_origins: AllowedOrigins::Any,
+            ..config
+        })
+        .unwrap();
+        assert!(allowed(&layer, "https://other.com"));
+        assert!(!allowed(&layer, "https://evil.example.com"));
+    }
+
+    #[test]
+    fn test_max_age_by_origin() {
+        let config = Config {
+            allowed_origins: AllowedOrigins::Mirror,
+            max_age: MaxAge::Seconds(Duration::from_secs(60)),
+            max_age_by_origin: vec![
+                OriginMaxAge {
+                    origins: AllowedOrigins::Exact(HashSet::from(
+                        ["https://a.example".to_string()],
+                    )),
+                    max_age: Duration::from_secs(600),
+                },
+                OriginMaxAge {
+                    origins: AllowedOrigins::Any,
+                    max_age: Duration::from_secs(5),
+                },
+            ],
+            ..credentialed_config()
+        };
+        let max_age = |config: &Config, origin| {
+            cors_headers(
+                CorsLayer::try_from(config.clone()).unwrap(),
+                preflight_from(origin),
+            )[http::header::ACCESS_CONTROL_MAX_AGE]
+                .clone()
+        };
+        assert_eq!(max_age(&config, "https://a.example"), "600");
+        assert_eq!(max_age(&config, "https://b.example"), "5");
+
+        let config = Config {
+            max_age_by_origin: config.max_age_by_origin[..1].to_vec(),
+            ..config
+        };
+        assert_eq!(max_age(&config, "https://b.example"), "60");
+    }
+
+    #[test]
+    fn test_origin_constructors() {
+        assert_eq!(
+            AllowedOrigins::exact("https://a.example"),
+            AllowedOrigins::Exact(HashSet::from(["https://a.example".to_string()]))
+        );
+        assert_eq!(
+            AllowedOrigins::regex(r"https://a\.example").unwrap(),
+            AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://a\.example"]).unwrap()).unwrap()
+            )
+        );
+        assert_eq!(
+            AllowedOrigins::regexes(&["a", "b"]).unwrap(),
+            AllowedOrigins::List(SerdeRegexSet::new(RegexSet::new(["b", "a"]).unwrap()).unwrap())
+        );
+        assert!(AllowedOrigins::regexes(&["a", "("]).is_err());
+    }
+
+    #[test]
+    fn test_origin_patterns_are_anchored() {
+        let origins = SerdeRegexSet::new(RegexSet::new([r"https://example\.com"]).unwrap())
+            .unwrap()
+            .anchored();
+        assert!(origins.is_match("https://example.com"));
+        assert!(!origins.is_match("https://example.com.evil.com"));
+        assert!(!origins.is_match("https://evil-https://example.com"));
+        assert_eq!(origins.patterns(), [r"^(?:https://example\.com)$"]);
+    }
+
+    #[test]
+    fn test_patterns_that_break_when_anchored_are_rejected() {
+        // the comment swallows the closing `)$` of the anchoring group
+        let pattern = r"(?x)https://a\.example # prod";
+        assert!(SerdeRegexSet::new(RegexSet::new([pattern]).unwrap()).is_err());
+        assert!(AllowedOrigins::try_list([pattern]).is_err());
+        #[cfg(feature = "serde")]
+        {
+            let error = serde_yaml::from_str::<Config>(&format!("allowed-origins: ['{pattern}']"))
+                .unwrap_err();
+            assert!(
+                error.to_string().contains("invalid origin pattern"),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_regex_set_equality_ignores_order() {
+        let set = |patterns: &[&str]| SerdeRegexSet::new(RegexSet::new(patterns).unwrap()).unwrap();
+        assert_eq!(set(&["a", "b"]), set(&["b", "a"]));
+        assert_ne!(set(&["a", "b"]), set(&["a"]));
+        assert_ne!(set(&["a"]), set(&["^a$"]));
+    }
+
+    fn credentialed_config() -> Config {
+        Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::List(HashSet::from([http::header::AUTHORIZATION])),
+            allowed_methods: AllowedMethods::List(HashSet::from([http::Method::GET])),
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://example\.com"]).unwrap()).unwrap(),
+            ),
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::List(HashSet::new()),
+            vary: Vary::default(),
+            on_origin_decision: None,
+        }
+    }
+
+    #[test]
+    fn test_try_from_accepts_credentials_without_wildcards() {
+        assert!(CorsLayer::try_from(credentialed_config()).is_ok());
+    }
+
+    #[test]
+    fn test_try_from_rejects_credentials_with_wildcards() {
+        let configs = [
+            (
+                Config {
+                    allowed_origins: AllowedOrigins::Any,
+                    ..credentialed_config()
+                },
+                "allowed-origins",
+            ),
+            (
+                Config {
+                    allowed_headers: AllowedHeaders::Any,
+                    ..credentialed_config()
+                },
+                "allowed-headers",
+            ),
+            (
+                Config {
+                    allowed_methods: AllowedMethods::Any,
+                    ..credentialed_config()
+                },
+                "allowed-methods",
+            ),
+            (
+                Config {
+                    expose_headers: ExposeHeaders::Any,
+                    ..credentialed_config()
+                },
+                "expose-headers",
+            ),
+        ];
+        for (config, expected) in configs {
+            match CorsLayer::try_from(config) {
+                Err(ConfigError::CredentialsWithWildcard { field }) => assert_eq!(field, expected),
+                other => panic!("expected a wildcard error for {expected}, got {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_crate() {
+        use std::collections::HashMap;
+
+        // nested values are set through `config`'s dotted key paths
+        let values: HashMap<&str, config::Value> = HashMap::from([
+            ("allow-credentials", true.into()),
+            ("allowed-headers", vec!["authorization"].into()),
+            ("allowed-methods", "mirror".into()),
+            ("allowed-origins.exact", vec!["https://example.com"].into()),
+            ("denied-origins", vec![r"https://evil\.example"].into()),
+            ("max-age", "1h".into()),
+            (
+                "max-age-by-origin[0].origins.wildcard",
+                vec!["https://*.example.com"].into(),
+            ),
+            ("max-age-by-origin[0].max-age", "10m".into()),
+            ("expose-headers", "none".into()),
+            ("vary", vec!["origin"].into()),
+        ]);
+        let config = values
+            .into_iter()
+            .fold(config::Config::builder(), |builder, (key, value)| {
+                builder.set_default(key, value).unwrap()
+            })
+            .build()
+            .unwrap()
+            .try_deserialize::<Config>()
+            .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                enabled: true,
+                version: CONFIG_VERSION,
+                allow_credentials: true,
+                allowed_headers: AllowedHeaders::List(HashSet::from([http::header::AUTHORIZATION])),
+                allowed_methods: AllowedMethods::Mirror,
+                allowed_origins: AllowedOrigins::exact("https://example.com"),
+                denied_origins: Some(
+                    SerdeRegexSet::new(RegexSet::new([r"https://evil\.example"]).unwrap()).unwrap()
+                ),
+                allow_private_network: false,
+                max_age: MaxAge::Seconds(Duration::from_secs(3600)),
+                max_age_by_origin: vec![OriginMaxAge {
+                    origins: AllowedOrigins::Wildcard(HashSet::from([
+                        "https://*.example.com".to_string()
+                    ])),
+                    max_age: Duration::from_secs(600),
+                }],
+                expose_headers: ExposeHeaders::None,
+                vary: Vary(HashSet::from([http::header::ORIGIN])),
+                on_origin_decision: None,
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_roundtrip() {
+        let config = Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::List(HashSet::from([
+                http::header::CONNECTION,
+                http::header::AUTHORIZATION,
+            ])),
+            allowed_methods: AllowedMethods::Mirror,
+            allowed_origins: AllowedOrigins::Any,
+            denied_origins: None,
+            allow_private_network: true,
+            max_age: MaxAge::Seconds(Duration::from_secs(3600)),
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::Any,
+            vary: Vary(HashSet::from([http::HeaderName::from_static("origin")])),
+            on_origin_decision: None,
+        };
+        let serialized = serde_yaml::to_string(&config).unwrap();
+        let deserialized: Config = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_enabled_by_default() {
+        assert!(serde_yaml::from_str::<Config>("{}").unwrap().enabled);
+        assert!(!serde_yaml::to_string(&Config::default())
+            .unwrap()
+            .contains("enabled"));
+
+        let config = Config {
+            enabled: false,
+            ..Config::default()
+        };
+        let serialized = serde_yaml::to_string(&config).unwrap();
+        assert!(serialized.contains("enabled: false"));
+        assert_eq!(serde_yaml::from_str::<Config>(&serialized).unwrap(), config);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_omitted_fields_use_defaults() {
+        let config: Config = serde_yaml::from_str("allow-credentials: true").unwrap();
+        assert_eq!(
+            config,
+            Config {
+                enabled: true,
+                allow_credentials: true,
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Config>("{}").unwrap(),
+            Config::default()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_wire_format() {
+        fn yaml<T: serde::Serialize>(value: T) -> String {
+            serde_yaml::to_string(&value).unwrap()
+        }
+        fn json<T: serde::Serialize>(value: T) -> String {
+            serde_json::to_string(&value).unwrap()
+        }
+
+        assert_eq!(yaml(AllowedOrigins::Any), "any\n");
+        assert_eq!(yaml(AllowedOrigins::Mirror), "mirror\n");
+        assert_eq!(yaml(AllowedHeaders::Any), "any\n");
+        assert_eq!(yaml(AllowedHeaders::Mirror), "mirror\n");
+        assert_eq!(yaml(AllowedMethods::Any), "any\n");
+        assert_eq!(yaml(AllowedMethods::Mirror), "mirror\n");
+        assert_eq!(yaml(ExposeHeaders::Any), "any\n");
+        assert_eq!(yaml(ExposeHeaders::None), "none\n");
+        assert_eq!(json(AllowedOrigins::Mirror), r#""mirror""#);
+        assert_eq!(json(AllowedMethods::Mirror), r#""mirror""#);
+        assert_eq!(json(ExposeHeaders::None), r#""none""#);
+        assert_eq!(
+            json(AllowedOrigins::exact("https://a.example")),
+            r#"{"exact":["https://a.example"]}"#
+        );
+        assert_eq!(
+            json(AllowedHeaders::MirrorList(HashSet::from([
+                http::header::AUTHORIZATION
+            ]))),
+            r#"{"mirror-list":["authorization"]}"#
+        );
+        assert_eq!(json(AllowedMethods::none()), "[]");
+
+        // unset optional fields and an empty `vary` are left out
+        assert_eq!(
+            yaml(Config::permissive()),
+            "allow-credentials: false\n\
+             allowed-headers: any\n\
+             allowed-methods: any\n\
+             allowed-origins: any\n\
+             allow-private-network: false\n\
+             expose-headers: any\n"
+        );
+        assert_eq!(
+            json(Config {
+                allowed_methods: AllowedMethods::Mirror,
+                max_age: MaxAge::Seconds(Duration::from_secs(600)),
+                vary: Vary(HashSet::from([http::header::ORIGIN])),
+                ..Config::default()
+            }),
+            r#"{"allow-credentials":false,"allowed-headers":[],"allowed-methods":"mirror","#
+                .to_string()
+                + r#""allowed-origins":[],"allow-private-network":false,"max-age":"10m","#
+                + r#""expose-headers":[],"vary":["origin"]}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialization_is_sorted() {
+        // each `HashSet` gets its own random iteration order, so build the
+        // config afresh every time
+        let config = || Config {
+            allowed_headers: AllowedHeaders::List(HashSet::from([
+                http::header::RANGE,
+                http::header::AUTHORIZATION,
+                http::header::CONTENT_TYPE,
+                http::header::ACCEPT,
+            ])),
+            allowed_methods: AllowedMethods::List(HashSet::from([
+                http::Method::PUT,
+                http::Method::GET,
+                http::Method::DELETE,
+                http::Method::POST,
+            ])),
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([
+                        r"https://c\.example",
+                        r"https://a\.example",
+                        r"https://b\.example",
+                    ])
+                    .unwrap(),
+                )
+                .unwrap(),
+            ),
+            vary: Vary(HashSet::from([
+                http::header::ORIGIN,
+                http::header::ACCESS_CONTROL_REQUEST_METHOD,
+                http::header::ACCESS_CONTROL_REQUEST_HEADERS,
+            ])),
+            ..Config::permissive()
+        };
+        let serialized = serde_yaml::to_string(&config()).unwrap();
+        for _ in 0..8 {
+            assert_eq!(serde_yaml::to_string(&config()).unwrap(), serialized);
+        }
+        assert!(serialized
+            .contains("allowed-headers:\n- accept\n- authorization\n- content-type\n- range\n"));
+        assert!(serialized.contains("allowed-methods:\n- DELETE\n- GET\n- POST\n- PUT\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_regex_origins_roundtrip() {
+        for patterns in [
+            &[r"https://a\.example", r"https://.*\.b\.example"][..],
+            &[r"https://a\.example"],
+            // look like the `any`/`mirror` keywords, but are patterns in a list
+            &["any"],
+            &["mirror"],
+        ] {
+            let origins = AllowedOrigins::regexes(patterns).unwrap();
+
+            let yaml = serde_yaml::to_string(&origins).unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<AllowedOrigins>(&yaml).unwrap(),
+                origins,
+                "{yaml}"
+            );
+
+            let json = serde_json::to_string(&origins).unwrap();
+            assert!(json.starts_with('['), "{json}");
+            assert_eq!(
+                serde_json::from_str::<AllowedOrigins>(&json).unwrap(),
+                origins,
+                "{json}"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_regex_flags_roundtrip() {
+        let origins = AllowedOrigins::regexes(&[r"(?i)https://app\.example"]).unwrap();
+        let yaml = serde_yaml::to_string(&origins).unwrap();
+        let roundtripped = serde_yaml::from_str::<AllowedOrigins>(&yaml).unwrap();
+        for origin in [
+            "https://app.example",
+            "https://APP.example",
+            "https://app.exampleX",
+        ] {
+            assert_eq!(
+                roundtripped.matcher().is_match_str(origin),
+                origins.matcher().is_match_str(origin),
+                "{origin}"
+            );
+        }
+        assert!(roundtripped.matcher().is_match_str("https://APP.example"));
+
+        // builder options aren't part of the patterns, so matching ignores
+        // them just like serialization does
+        let origins = AllowedOrigins::List(
+            SerdeRegexSet::new(
+                regex::RegexSetBuilder::new([r"https://app\.example"])
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap(),
+        );
+        assert!(!origins.matcher().is_match_str("https://APP.example"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_keywords_and_lists_are_told_apart() {
+        fn parse<T: serde::de::DeserializeOwned>(yaml: &str) -> Result<T, serde_yaml::Error> {
+            serde_yaml::from_str(yaml)
+        }
+
+        assert_eq!(
+            parse::<AllowedOrigins>("mirror").unwrap(),
+            AllowedOrigins::Mirror
+        );
+        assert_eq!(
+            parse::<AllowedOrigins>("[mirror]").unwrap(),
+            AllowedOrigins::regex("mirror").unwrap()
+        );
+        assert_eq!(
+            parse::<AllowedOrigins>("Mirror").unwrap(),
+            AllowedOrigins::regex("Mirror").unwrap()
+        );
+        assert_eq!(
+            parse::<AllowedOrigins>("https://example.com").unwrap(),
+            AllowedOrigins::regex("https://example.com").unwrap()
+        );
+        assert_eq!(
+            parse::<AllowedOrigins>("exact: https://example.com").unwrap(),
+            AllowedOrigins::exact("https://example.com")
+        );
+
+        assert_eq!(parse::<AllowedHeaders>("any").unwrap(), AllowedHeaders::Any);
+        assert_eq!(
+            parse::<AllowedHeaders>("[mirror]").unwrap(),
+            AllowedHeaders::List(HashSet::from([http::HeaderName::from_static("mirror")]))
+        );
+        assert_eq!(
+            parse::<AllowedHeaders>("authorization").unwrap(),
+            AllowedHeaders::List(HashSet::from([http::header::AUTHORIZATION]))
+        );
+
+        assert_eq!(
+            parse::<AllowedMethods>("mirror").unwrap(),
+            AllowedMethods::Mirror
+        );
+        assert_eq!(
+            parse::<AllowedMethods>("[any]").unwrap(),
+            AllowedMethods::List(HashSet::from([http::Method::from_bytes(b"ANY").unwrap()]))
+        );
+        assert_eq!(
+            parse::<AllowedMethods>("GET").unwrap(),
+            AllowedMethods::List(HashSet::from([http::Method::GET]))
+        );
+
+        assert_eq!(parse::<ExposeHeaders>("none").unwrap(), ExposeHeaders::None);
+        assert_eq!(
+            parse::<ExposeHeaders>("[any]").unwrap(),
+            ExposeHeaders::List(HashSet::from([http::HeaderName::from_static("any")]))
+        );
+        assert_eq!(
+            parse::<ExposeHeaders>("mirror").unwrap(),
+            ExposeHeaders::List(HashSet::from([http::HeaderName::from_static("mirror")]))
+        );
+        assert!(parse::<ExposeHeaders>("1").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_method_aliases() {
+        let methods = |yaml| match serde_yaml::from_str(yaml).unwrap() {
+            AllowedMethods::List(methods) => methods,
+            other => panic!("expected a list, got {other:?}"),
+        };
+        assert_eq!(
+            methods("[get, Post, pUt]"),
+            HashSet::from([http::Method::GET, http::Method::POST, http::Method::PUT])
+        );
+        assert_eq!(methods("['*']"), HashSet::from(STANDARD_METHODS));
+        assert_eq!(methods("[all, purge]"), {
+            let mut expected = HashSet::from(STANDARD_METHODS);
+            expected.insert(http::Method::from_bytes(b"PURGE").unwrap());
+            expected
+        });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_exact_origins_deserialize() {
+        let origins: AllowedOrigins =
+            serde_yaml::from_str("exact: [https://a.example, https://b.example]").unwrap();
+        assert_eq!(
+            origins,
+            AllowedOrigins::Exact(HashSet::from([
+                "https://a.example".to_string(),
+                "https://b.example".to_string(),
+            ]))
+        );
+        let serialized = serde_yaml::to_string(&origins).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<AllowedOrigins>(&serialized).unwrap(),
+            origins
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_wildcard_origins_deserialize() {
+        let origins: AllowedOrigins =
+            serde_yaml::from_str("wildcard: ['https://*.a.example']").unwrap();
+        assert_eq!(
+            origins,
+            AllowedOrigins::Wildcard(HashSet::from(["https://*.a.example".to_string()]))
+        );
+        assert!(serde_yaml::from_str::<AllowedOrigins>("{wildcard: [], exact: []}").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_max_age_by_origin_deserialize() {
+        let groups: Vec<OriginMaxAge> =
+            serde_yaml::from_str("[{origins: {exact: [https://a.example]}, max-age: 10m}]")
+                .unwrap();
+        assert_eq!(
+            groups,
+            [OriginMaxAge {
+                origins: AllowedOrigins::Exact(HashSet::from(["https://a.example".to_string()])),
+                max_age: Duration::from_secs(600),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mirror_list_headers() {
+        let layer = CorsLayer::try_from(Config {
+            allowed_headers: AllowedHeaders::MirrorList(HashSet::from([
+                http::header::AUTHORIZATION,
+            ])),
+            ..credentialed_config()
+        })
+        .unwrap();
+        let mut preflight = preflight_from("https://example.com");
+        preflight.headers_mut().insert(
+            http::header::ACCESS_CONTROL_REQUEST_HEADERS,
+            http::HeaderValue::from_static("authorization"),
+        );
+        assert_eq!(
+            cors_headers(layer, preflight)[http::header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "authorization"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mirror_list_headers_roundtrip() {
+        let headers: AllowedHeaders = serde_yaml::from_str("mirror-list: [Authorization]").unwrap();
+        assert_eq!(
+            headers,
+            AllowedHeaders::MirrorList(HashSet::from([http::header::AUTHORIZATION]))
+        );
+        let serialized = serde_yaml::to_string(&headers).unwrap();
+        assert_eq!(serialized, "mirror-list:\n- authorization\n");
+        assert_eq!(
+            serde_yaml::from_str::<AllowedHeaders>(&serialized).unwrap(),
+            headers
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mirror_capped_headers_roundtrip() {
+        let headers: AllowedHeaders = serde_yaml::from_str("mirror-capped: {}").unwrap();
+        assert_eq!(headers, AllowedHeaders::MirrorCapped(HeaderCap::default()));
+        let headers: AllowedHeaders =
+            serde_yaml::from_str("mirror-capped: { max-headers: 8 }").unwrap();
+        assert_eq!(
+            headers,
+            AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers: 8,
+                max_bytes: HeaderCap::DEFAULT_MAX_BYTES,
+            })
+        );
+        let serialized = serde_yaml::to_string(&headers).unwrap();
+        assert_eq!(
+            serialized,
+            "mirror-capped:\n  max-headers: 8\n  max-bytes: 1024\n"
+        );
+        assert_eq!(
+            serde_yaml::from_str::<AllowedHeaders>(&serialized).unwrap(),
+            headers
+        );
+        assert!(serde_yaml::from_str::<AllowedHeaders>("mirror-capped: { max: 8 }").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_regex_headers_roundtrip() {
+        let headers: AllowedHeaders = serde_yaml::from_str("regex: x-myapp-.*").unwrap();
+        assert_eq!(
+            headers,
+            AllowedHeaders::Regex(
+                SerdeRegexSet::new(RegexSet::new(["x-myapp-.*"]).unwrap()).unwrap()
+            )
+        );
+        let serialized = serde_yaml::to_string(&headers).unwrap();
+        assert_eq!(serialized, "regex:\n- x-myapp-.*\n");
+        assert_eq!(
+            serde_yaml::from_str::<AllowedHeaders>(&serialized).unwrap(),
+            headers
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_localhost_any_roundtrip() {
+        assert_eq!(
+            serde_yaml::to_string(&AllowedOrigins::LocalhostAny).unwrap(),
+            "any-localhost\n"
+        );
+        assert_eq!(
+            serde_yaml::from_str::<AllowedOrigins>("any-localhost").unwrap(),
+            AllowedOrigins::LocalhostAny
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_comma_separated_lists() {
+        assert_eq!(
+            serde_yaml::from_str::<AllowedMethods>("'GET, POST'").unwrap(),
+            AllowedMethods::list([http::Method::GET, http::Method::POST])
+        );
+        assert_eq!(
+            serde_json::from_str::<AllowedHeaders>(r#""content-type,x-request-id, ""#).unwrap(),
+            AllowedHeaders::list([
+                http::header::CONTENT_TYPE,
+                http::HeaderName::from_static("x-request-id")
+            ])
+        );
+        assert_eq!(
+            serde_yaml::from_str::<AllowedMethods>("[GET, 'PUT,DELETE']").unwrap(),
+            AllowedMethods::list([http::Method::GET, http::Method::PUT, http::Method::DELETE])
+        );
+        // a lone keyword still picks the variant rather than a one-entry list
+        assert_eq!(
+            serde_yaml::from_str::<AllowedMethods>("mirror").unwrap(),
+            AllowedMethods::Mirror
+        );
+    }
+
+    #[test]
+    fn test_expose_same_as_allowed() {
+        let config = Config {
+            allowed_headers: AllowedHeaders::list([
+                http::header::AUTHORIZATION,
+                http::header::CONTENT_TYPE,
+            ]),
+            expose_headers: ExposeHeaders::SameAsAllowed,
+            ..credentialed_config()
+        };
+        assert_eq!(
+            config.resolved_expose_headers(),
+            ExposeHeaders::list([http::header::AUTHORIZATION, http::header::CONTENT_TYPE])
+        );
+        let headers = cors_headers(
+            CorsLayer::try_from(config).unwrap(),
+            request_from("https://example.com"),
+        );
+        let mut exposed = headers[http::header::ACCESS_CONTROL_EXPOSE_HEADERS]
+            .to_str()
+            .unwrap()
+            .split(',')
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        exposed.sort_unstable();
+        assert_eq!(exposed, ["authorization", "content-type"]);
+
+        // nothing to copy from `mirror`, and `*` isn't the same thing
+        let config = Config {
+            allowed_headers: AllowedHeaders::Mirror,
+            expose_headers: ExposeHeaders::SameAsAllowed,
+            ..credentialed_config()
+        };
+        assert_eq!(config.resolved_expose_headers(), ExposeHeaders::None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_expose_headers_none_is_distinct() {
+        assert_eq!(
+            serde_yaml::to_string(&ExposeHeaders::none()).unwrap(),
+            "none\n"
+        );
+        assert_eq!(
+            serde_yaml::from_str::<ExposeHeaders>("none").unwrap(),
+            ExposeHeaders::None
+        );
+        assert_eq!(
+            serde_yaml::from_str::<ExposeHeaders>("[]").unwrap(),
+            ExposeHeaders::List(HashSet::new())
+        );
+        assert_eq!(
+            serde_yaml::from_str::<ExposeHeaders>("same-as-allowed").unwrap(),
+            ExposeHeaders::SameAsAllowed
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_dynamic_origins_are_not_serializable() {
+        let origins = AllowedOrigins::Dynamic(OriginPredicate::new(|_, _| true));
+        assert!(serde_yaml::to_string(&origins).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_exact_origins_are_validated() {
+        let error = serde_yaml::from_str::<AllowedOrigins>(
+            "exact: [https://example.com, 'https://example.com/']",
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("invalid origin `https://example.com/`"),
+            "{error}"
+        );
+        let error = serde_yaml::from_str::<AllowedOrigins>("exact: example.com").unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "invalid origin `example.com`, it needs a scheme such as `https://example.com`"
+            ),
+            "{error}"
+        );
+        assert_eq!(
+            serde_yaml::from_str::<AllowedOrigins>("exact: https://example.com").unwrap(),
+            AllowedOrigins::exact("https://example.com")
+        );
+        // also inside a config, and for regex lists
+        let error =
+            serde_yaml::from_str::<Config>("allowed-origins: {exact: example.com}").unwrap_err();
+        assert!(error.to_string().contains("needs a scheme"), "{error}");
+        let error = serde_yaml::from_str::<Config>("allowed-origins: ['https://(']").unwrap_err();
+        assert!(
+            error.to_string().contains("invalid origin pattern"),
+            "{error}"
+        );
+        let error =
+            serde_yaml::from_str::<AllowedOrigins>("{exact: [], wildcard: []}").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("single `exact` or `wildcard` key"),
+            "{error}"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_max_age_seconds_or_humantime() {
+        let max_age = |yaml: &str| {
+            serde_yaml::from_str::<Config>(&format!(
+                "{{allowed-origins: any, allowed-headers: any, allowed-methods: any, \
+                 expose-headers: any, {yaml}}}"
+            ))
+            .map(|config| config.max_age.as_duration())
+        };
+        assert_eq!(
+            max_age("max-age: 3600").unwrap(),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            max_age("max-age: 1h").unwrap(),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(max_age("max-age: null").unwrap(), None);
+        assert!(max_age("max-age: -1").is_err());
+        assert!(max_age("max-age: soon").is_err());
+        assert_eq!(
+            max_age("max-age: {secs: 90}").unwrap(),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            max_age("max-age: {millis: 1500}").unwrap(),
+            Some(Duration::from_millis(1500))
+        );
+        assert!(max_age("max-age: {}").is_err());
+        assert!(max_age("max-age: {minutes: 5}").is_err());
+        assert!(max_age("max-age: {secs: 1, millis: 1}").is_err());
+        assert_eq!(max_age("max-age: disabled").unwrap(), None);
+        assert_eq!(
+            max_age("max-age: browser-max").unwrap(),
+            Some(MAX_BROWSER_MAX_AGE)
+        );
+        let config = serde_yaml::from_str::<Config>("max-age: browser-max").unwrap();
+        assert_eq!(config.max_age, MaxAge::BrowserMax);
+        assert_eq!(
+            serde_yaml::to_string(&config.max_age).unwrap(),
+            "browser-max\n"
+        );
+
+        let group: OriginMaxAge =
+            serde_json::from_str(r#"{"origins": "any", "max-age": 600}"#).unwrap();
+        assert_eq!(group.max_age, Duration::from_secs(600));
+        assert_eq!(
+            serde_json::to_string(&group).unwrap(),
+            r#"{"origins":"any","max-age":"10m"}"#
+        );
+        let group: OriginMaxAge =
+            serde_json::from_str(r#"{"origins": "any", "max-age": "browser-max"}"#).unwrap();
+        assert_eq!(group.max_age, MAX_BROWSER_MAX_AGE);
+        assert!(serde_json::from_str::<OriginMaxAge>(
+            r#"{"origins": "any", "max-age": "disabled"}"#
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_invalid_values_name_the_offender() {
+        // untagged enums swallow the inner error, so go through the field helpers directly
+        let error = serde_header_name::deserialize(serde_yaml::Deserializer::from_str(
+            "[x-ok, 'bad header']",
+        ))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("invalid header name `bad header`"));
+
+        let error = serde_method::deserialize(serde_yaml::Deserializer::from_str("[GET, 'G(E)T']"))
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid HTTP method `G(E)T`"));
+
+        let error =
+            serde_regex_set::deserialize(serde_yaml::Deserializer::from_str("['https://(']"))
+                .unwrap_err();
+        assert!(error.to_string().contains("invalid origin pattern"));
     }
 }
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+
+use http::{request::Parts, HeaderValue};
+use regex::RegexSet;
+
+use crate::{AllowedOrigins, Config, OriginPredicate, SharedOrigins};
+
+/// The origin check behind an [`AllowedOrigins`] policy, usable outside of a
+/// `CorsLayer`, e.g. to vet the `Origin` of a WebSocket upgrade.
+///
+/// Patterns are compiled once when the matcher is created, so it is cheap to
+/// call [`OriginMatcher::is_match`] repeatedly and to clone the matcher.
+#[derive(Debug, Clone)]
+pub struct OriginMatcher(MatcherInner);
+
+#[derive(Debug, Clone)]
+enum MatcherInner {
+    Any,
+    Exact(HashSet<String>),
+    Regex(RegexSet),
+    Dynamic(OriginPredicate),
+    Shared(SharedOrigins),
+}
+
+impl OriginMatcher {
+    /// Whether `origin` is allowed. `Any` and `Mirror` policies allow every
+    /// origin.
+    ///
+    /// A [`Dynamic`](AllowedOrigins::Dynamic) policy needs the request to
+    /// decide, so without one nothing matches; use
+    /// [`OriginMatcher::is_match_request`] instead.
+    pub fn is_match(&self, origin: &HeaderValue) -> bool {
+        origin
+            .to_str()
+            .is_ok_and(|origin| self.is_match_str(origin))
+    }
+
+    /// Like [`OriginMatcher::is_match`], for an origin that is already a string
+    pub fn is_match_str(&self, origin: &str) -> bool {
+        match &self.0 {
+            MatcherInner::Any => true,
+            MatcherInner::Exact(origins) => origins.contains(origin),
+            MatcherInner::Regex(origins) => origins.is_match(origin),
+            MatcherInner::Shared(origins) => origins.is_match(origin),
+            MatcherInner::Dynamic(_) => false,
+        }
+    }
+
+    /// Like [`OriginMatcher::is_match`], also passing the rest of the request
+    /// to a [`Dynamic`](AllowedOrigins::Dynamic) policy
+    pub fn is_match_request(&self, origin: &HeaderValue, parts: &Parts) -> bool {
+        match &self.0 {
+            MatcherInner::Dynamic(predicate) => predicate.call(origin, parts),
+            _ => self.is_match(origin),
+        }
+    }
+}
+
+/// The regex behind [`AllowedOrigins::LocalhostAny`], without anchors
+pub(crate) const LOCALHOST_PATTERN: &str =
+    r"https?://(?:localhost|127\.0\.0\.1|\[::1\])(?::[0-9]{1,5})?";
+
+impl From<AllowedOrigins> for OriginMatcher {
+    fn from(value: AllowedOrigins) -> Self {
+        OriginMatcher(match value {
+            AllowedOrigins::Any | AllowedOrigins::Mirror => MatcherInner::Any,
+            AllowedOrigins::Exact(origins) => {
+                MatcherInner::Exact(origins.into_iter().map(to_ascii).collect())
+            }
+            AllowedOrigins::Wildcard(patterns) => MatcherInner::Regex(
+                RegexSet::new(
+                    patterns
+                        .into_iter()
+                        .map(|pattern| format!("^{}$", wildcard_pattern(&to_ascii(pattern)))),
+                )
+                .expect("escaped wildcard patterns are valid regexes"),
+            ),
+            AllowedOrigins::LocalhostAny => MatcherInner::Regex(
+                RegexSet::new([format!("^{LOCALHOST_PATTERN}$")])
+                    .expect("the localhost pattern is a valid regex"),
+            ),
+            AllowedOrigins::List(origins) => MatcherInner::Regex(origins.anchored()),
+            AllowedOrigins::Dynamic(predicate) => MatcherInner::Dynamic(predicate),
+            AllowedOrigins::Shared(origins) => MatcherInner::Shared(origins),
+        })
+    }
+}
+
+impl AllowedOrigins {
+    /// Build the [`OriginMatcher`] used for this policy
+    pub fn matcher(&self) -> OriginMatcher {
+        OriginMatcher::from(self.clone())
+    }
+}
+
+impl Config {
+    /// Whether the layer would allow `origin`, taking `denied_origins` into
+    /// account, without building it.
+    ///
+    /// `Any` and `Mirror` allow every origin that isn't denied; `Mirror`
+    /// reflects whatever is requested. A [`Dynamic`](AllowedOrigins::Dynamic)
+    /// policy needs the request, so nothing is allowed here. Matchers are
+    /// built on every call, compiling wildcard origins again, so build an
+    /// [`OriginMatcher`] with [`AllowedOrigins::matcher`] for hot paths.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let denied = self.denied_origins.as_ref().is_some_and(|denied| {
+            OriginMatcher::from(AllowedOrigins::List(denied.clone())).is_match_str(origin)
+        });
+        !denied && self.allowed_origins.matcher().is_match_str(origin)
+    }
+}
+
+/// Convert the host of `origin` to its ASCII (punycode) form, which is what
+/// browsers send in the `Origin` header, e.g. `https://bücher.example`
+/// becomes `https://xn--bcher-kva.example`.
+///
+/// Only non-ASCII labels are converted, so `*` labels in wildcard patterns are
+/// left alone, as is anything that isn't a valid domain label.
+#[cfg(feature = "idna")]
+pub(crate) fn to_ascii(origin: String) -> String {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return origin;
+    };
+    if authority.is_ascii() {
+        return origin;
+    }
+    let (host, port) = authority.split_at(authority.rfind(':').unwrap_or(authority.len()));
+    let host = host
+        .split('.')
+        .map(|label| {
+            if label.is_ascii() {
+                label.to_string()
+            } else {
+                idna::domain_to_ascii(label).unwrap_or_else(|_| label.to_string())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    format!("{scheme}://{host}{port}")
+}
+
+/// Without the `idna` feature, origins are compared exactly as configured
+#[cfg(not(feature = "idna"))]
+pub(crate) fn to_ascii(origin: String) -> String {
+    origin
+}
+
+/// Translate a wildcard origin like `https://*.example.com` into a regex,
+/// without anchors, where each `*` matches exactly one DNS label
+pub(crate) fn wildcard_pattern(pattern: &str) -> String {
+    pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join("[A-Za-z0-9-]+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerdeRegexSet;
+
+    #[test]
+    fn test_matcher() {
+        let matcher = AllowedOrigins::List(
+            SerdeRegexSet::new(RegexSet::new([r"https://.*\.example\.com"]).unwrap()).unwrap(),
+        )
+        .matcher();
+        assert!(matcher.is_match(&HeaderValue::from_static("https://api.example.com")));
+        assert!(!matcher.is_match(&HeaderValue::from_static("https://api.example.com.evil")));
+        assert!(!matcher.is_match(&HeaderValue::from_bytes(b"https://\xff.example.com").unwrap()));
+
+        let matcher =
+            AllowedOrigins::Exact(HashSet::from(["https://a.example".to_string()])).matcher();
+        assert!(matcher.is_match_str("https://a.example"));
+        assert!(!matcher.is_match_str("https://b.example"));
+
+        assert!(AllowedOrigins::Mirror
+            .matcher()
+            .is_match_str("https://anything.example"));
+    }
+
+    #[test]
+    fn test_allows_origin() {
+        let config = Config {
+            allowed_origins: AllowedOrigins::Mirror,
+            ..Config::default()
+        };
+        assert!(config.allows_origin("https://anything.example"));
+
+        let config = Config {
+            allowed_origins: AllowedOrigins::Any,
+            denied_origins: Some(
+                SerdeRegexSet::new(RegexSet::new([r"https://evil\.example"]).unwrap()).unwrap(),
+            ),
+            ..Config::default()
+        };
+        assert!(config.allows_origin("https://good.example"));
+        assert!(!config.allows_origin("https://evil.example"));
+
+        let config = Config {
+            allowed_origins: AllowedOrigins::exact("https://a.example"),
+            ..Config::default()
+        };
+        assert!(config.allows_origin("https://a.example"));
+        assert!(!config.allows_origin("https://b.example"));
+        assert!(!Config::default().allows_origin("https://a.example"));
+    }
+
+    #[test]
+    fn test_host_only() {
+        let origins = AllowedOrigins::host_only(&["app.example.com", "[::1]"]);
+        assert_eq!(
+            origins,
+            AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([
+                        r"https?://app\.example\.com(?::[0-9]{1,5})?",
+                        r"https?://\[::1\](?::[0-9]{1,5})?",
+                    ])
+                    .unwrap()
+                )
+                .unwrap()
+            )
+        );
+        let matcher = origins.matcher();
+        for origin in [
+            "http://app.example.com",
+            "https://app.example.com",
+            "https://app.example.com:8443",
+            "http://[::1]:3000",
+        ] {
+            assert!(matcher.is_match_str(origin), "{origin}");
+        }
+        for origin in [
+            "https://api.app.example.com",
+            "https://appXexample.com",
+            "ftp://app.example.com",
+            "https://app.example.com:",
+        ] {
+            assert!(!matcher.is_match_str(origin), "{origin}");
+        }
+    }
+
+    #[test]
+    fn test_localhost_any() {
+        let matcher = AllowedOrigins::LocalhostAny.matcher();
+        for origin in [
+            "http://localhost",
+            "http://localhost:3000",
+            "https://localhost:8443",
+            "http://127.0.0.1:5173",
+            "http://[::1]:8080",
+        ] {
+            assert!(matcher.is_match_str(origin), "{origin}");
+        }
+        for origin in [
+            "http://localhost.evil.example",
+            "http://localhost:3000.evil.example",
+            "http://127.0.0.2:3000",
+            "ws://localhost:3000",
+            "http://evil.example/http://localhost",
+        ] {
+            assert!(!matcher.is_match_str(origin), "{origin}");
+        }
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn test_unicode_origins_match_punycode() {
+        let matcher = AllowedOrigins::exact("https://bücher.example:8443").matcher();
+        assert!(matcher.is_match_str("https://xn--bcher-kva.example:8443"));
+        assert!(!matcher.is_match_str("https://bucher.example:8443"));
+
+        let matcher =
+            AllowedOrigins::Wildcard(HashSet::from(["https://*.bücher.example".to_string()]))
+                .matcher();
+        assert!(matcher.is_match_str("https://shop.xn--bcher-kva.example"));
+        assert!(!matcher.is_match_str("https://xn--bcher-kva.example"));
+    }
+}
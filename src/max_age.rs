@@ -0,0 +1,63 @@
+//! How long browsers may cache preflight responses
+
+use std::time::Duration;
+
+use crate::MAX_BROWSER_MAX_AGE;
+
+/// The `Access-Control-Max-Age` policy of a [`Config`](crate::Config).
+///
+/// In config files this is either a duration (see
+/// [`Config::max_age`](crate::Config::max_age)) or one of the keywords
+/// `disabled` and `browser-max`. Code that used the earlier
+/// `Option<Duration>` can convert with `MaxAge::from`, and read the
+/// duration back with [`MaxAge::as_duration`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MaxAge {
+    /// Send no `Access-Control-Max-Age` header, so browsers fall back to
+    /// their own default (5 seconds)
+    #[default]
+    Disabled,
+    /// Cache preflights for this long. Browsers only see whole seconds, so
+    /// milliseconds are rounded down when sent, and zero tells them not to
+    /// cache at all.
+    Seconds(Duration),
+    /// Cache preflights for [`MAX_BROWSER_MAX_AGE`], the longest any major
+    /// browser honours. There is no way to cache a preflight forever, and
+    /// longer values are clamped by browsers anyway.
+    BrowserMax,
+}
+
+impl MaxAge {
+    /// The duration sent, or `None` if no header is sent
+    pub const fn as_duration(&self) -> Option<Duration> {
+        match self {
+            MaxAge::Disabled => None,
+            MaxAge::Seconds(max_age) => Some(*max_age),
+            MaxAge::BrowserMax => Some(MAX_BROWSER_MAX_AGE),
+        }
+    }
+
+    pub const fn is_disabled(&self) -> bool {
+        matches!(self, MaxAge::Disabled)
+    }
+
+    /// `self`, or `fallback` if `self` is disabled, like [`Option::or`]
+    pub const fn or(self, fallback: MaxAge) -> MaxAge {
+        match self {
+            MaxAge::Disabled => fallback,
+            max_age => max_age,
+        }
+    }
+}
+
+impl From<Duration> for MaxAge {
+    fn from(value: Duration) -> Self {
+        MaxAge::Seconds(value)
+    }
+}
+
+impl From<Option<Duration>> for MaxAge {
+    fn from(value: Option<Duration>) -> Self {
+        value.map_or(MaxAge::Disabled, MaxAge::Seconds)
+    }
+}
@@ -0,0 +1,288 @@
+//! Layering one config on top of another
+
+use std::collections::BTreeSet;
+
+use regex::RegexSet;
+
+use crate::{
+    matcher::{to_ascii, wildcard_pattern, LOCALHOST_PATTERN},
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ExposeHeaders, OriginMatcher,
+    OriginPredicate, SerdeRegexSet, Vary,
+};
+
+impl Config {
+    /// Layer `other` on top of `self`, with `other` winning every field.
+    ///
+    /// Scalars and lists are taken from `other` as-is. The only fields that
+    /// can be "unset" are `denied_origins`, `max_age`, `max_age_by_origin`
+    /// and `on_origin_decision`, so a `None` or empty value there keeps
+    /// `self`'s.
+    pub fn merge_replace(self, other: Config) -> Config {
+        Config {
+            denied_origins: other.denied_origins.or(self.denied_origins),
+            max_age: other.max_age.or(self.max_age),
+            on_origin_decision: other.on_origin_decision.or(self.on_origin_decision),
+            max_age_by_origin: if other.max_age_by_origin.is_empty() {
+                self.max_age_by_origin
+            } else {
+                other.max_age_by_origin
+            },
+            ..other
+        }
+    }
+
+    /// Layer `other` on top of `self`, combining lists instead of replacing
+    /// them.
+    ///
+    /// Scalars follow [`Config::merge_replace`]. When both sides hold the same
+    /// kind of list (e.g. both [`AllowedOrigins::Exact`]) the result contains
+    /// the entries of both. Otherwise, for example when either side is `Any`
+    /// or `Mirror`, `other` wins.
+    pub fn merge_union(self, other: Config) -> Config {
+        Config {
+            enabled: other.enabled,
+            version: other.version,
+            allow_credentials: other.allow_credentials,
+            allowed_headers: match (self.allowed_headers, other.allowed_headers) {
+                (AllowedHeaders::List(mut headers), AllowedHeaders::List(other)) => {
+                    headers.extend(other);
+                    AllowedHeaders::List(headers)
+                }
+                (AllowedHeaders::MirrorList(mut headers), AllowedHeaders::MirrorList(other)) => {
+                    headers.extend(other);
+                    AllowedHeaders::MirrorList(headers)
+                }
+                (AllowedHeaders::Regex(patterns), AllowedHeaders::Regex(other)) => {
+                    AllowedHeaders::Regex(union_patterns(&patterns, &other))
+                }
+                (_, other) => other,
+            },
+            allowed_methods: match (self.allowed_methods, other.allowed_methods) {
+                (AllowedMethods::List(mut methods), AllowedMethods::List(other)) => {
+                    methods.extend(other);
+                    AllowedMethods::List(methods)
+                }
+                (_, other) => other,
+            },
+            allowed_origins: match (self.allowed_origins, other.allowed_origins) {
+                (AllowedOrigins::Exact(mut origins), AllowedOrigins::Exact(other)) => {
+                    origins.extend(other);
+                    AllowedOrigins::Exact(origins)
+                }
+                (AllowedOrigins::Wildcard(mut origins), AllowedOrigins::Wildcard(other)) => {
+                    origins.extend(other);
+                    AllowedOrigins::Wildcard(origins)
+                }
+                (AllowedOrigins::List(origins), AllowedOrigins::List(other)) => {
+                    AllowedOrigins::List(union_patterns(&origins, &other))
+                }
+                (_, other) => other,
+            },
+            denied_origins: match (self.denied_origins, other.denied_origins) {
+                (Some(denied), Some(other)) => Some(union_patterns(&denied, &other)),
+                (denied, other) => other.or(denied),
+            },
+            allow_private_network: other.allow_private_network,
+            max_age: other.max_age.or(self.max_age),
+            // `other`'s groups go first so they take precedence
+            max_age_by_origin: other
+                .max_age_by_origin
+                .into_iter()
+                .chain(self.max_age_by_origin)
+                .collect(),
+            expose_headers: match (self.expose_headers, other.expose_headers) {
+                (ExposeHeaders::List(mut headers), ExposeHeaders::List(other)) => {
+                    headers.extend(other);
+                    ExposeHeaders::List(headers)
+                }
+                (_, other) => other,
+            },
+            vary: Vary(self.vary.0.into_iter().chain(other.vary.0).collect()),
+            on_origin_decision: other.on_origin_decision.or(self.on_origin_decision),
+        }
+    }
+}
+
+impl AllowedOrigins {
+    /// Allow an origin if either `self` or `other` allows it.
+    ///
+    /// `Any` on either side wins outright, then `Mirror`, which allows every
+    /// origin by reflecting it. Lists of the same kind are combined as in
+    /// [`Config::merge_union`]; any other mix of lists becomes a regex
+    /// [`List`](AllowedOrigins::List) with the equivalent patterns, so the
+    /// result stays serializable. Only a [`Dynamic`](AllowedOrigins::Dynamic)
+    /// or [`Shared`](AllowedOrigins::Shared) side makes the result dynamic,
+    /// which keeps following replacements of the shared list.
+    pub fn or(self, other: AllowedOrigins) -> AllowedOrigins {
+        match (self, other) {
+            (AllowedOrigins::Any, _) | (_, AllowedOrigins::Any) => AllowedOrigins::Any,
+            (AllowedOrigins::Mirror, _) | (_, AllowedOrigins::Mirror) => AllowedOrigins::Mirror,
+            (AllowedOrigins::Exact(mut origins), AllowedOrigins::Exact(other)) => {
+                origins.extend(other);
+                AllowedOrigins::Exact(origins)
+            }
+            (AllowedOrigins::Wildcard(mut origins), AllowedOrigins::Wildcard(other)) => {
+                origins.extend(other);
+                AllowedOrigins::Wildcard(origins)
+            }
+            (AllowedOrigins::LocalhostAny, AllowedOrigins::LocalhostAny) => {
+                AllowedOrigins::LocalhostAny
+            }
+            (origins @ (AllowedOrigins::Dynamic(_) | AllowedOrigins::Shared(_)), other)
+            | (origins, other @ (AllowedOrigins::Dynamic(_) | AllowedOrigins::Shared(_))) => {
+                let (origins, other) = (OriginMatcher::from(origins), OriginMatcher::from(other));
+                AllowedOrigins::Dynamic(OriginPredicate::new(move |origin, parts| {
+                    origins.is_match_request(origin, parts) || other.is_match_request(origin, parts)
+                }))
+            }
+            (origins, other) => AllowedOrigins::List(union_patterns(
+                &regex_patterns(origins),
+                &regex_patterns(other),
+            )),
+        }
+    }
+}
+
+/// The regex [`AllowedOrigins::List`] equivalent of a non-dynamic list kind
+fn regex_patterns(origins: AllowedOrigins) -> SerdeRegexSet {
+    let patterns = match origins {
+        AllowedOrigins::Exact(origins) => origins
+            .into_iter()
+            .map(|origin| regex::escape(&to_ascii(origin)))
+            .collect(),
+        AllowedOrigins::Wildcard(patterns) => patterns
+            .into_iter()
+            .map(|pattern| wildcard_pattern(&to_ascii(pattern)))
+            .collect(),
+        AllowedOrigins::LocalhostAny => vec![LOCALHOST_PATTERN.to_string()],
+        AllowedOrigins::List(patterns) => return patterns,
+        AllowedOrigins::Any
+        | AllowedOrigins::Mirror
+        | AllowedOrigins::Dynamic(_)
+        | AllowedOrigins::Shared(_) => {
+            unreachable!("handled by `AllowedOrigins::or`")
+        }
+    };
+    RegexSet::new(patterns)
+        .and_then(SerdeRegexSet::new)
+        .expect("escaped patterns are valid regexes")
+}
+
+fn union_patterns(set: &SerdeRegexSet, other: &SerdeRegexSet) -> SerdeRegexSet {
+    let patterns = set
+        .patterns()
+        .iter()
+        .chain(other.patterns())
+        .collect::<BTreeSet<_>>();
+    RegexSet::new(patterns)
+        .and_then(SerdeRegexSet::new)
+        .expect("patterns were already compiled and anchored once")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use http::{header, Method};
+
+    use super::*;
+    use crate::{ConfigBuilder, MaxAge};
+    use pretty_assertions::assert_eq;
+
+    fn base() -> Config {
+        ConfigBuilder::new()
+            .allow_origin(AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://a\.example"]).unwrap()).unwrap(),
+            ))
+            .allow_header(header::AUTHORIZATION)
+            .allow_method(Method::GET)
+            .max_age(Duration::from_secs(60))
+            .build()
+            .unwrap()
+    }
+
+    fn overlay() -> Config {
+        ConfigBuilder::new()
+            .allow_credentials(true)
+            .allow_origin(AllowedOrigins::List(
+                SerdeRegexSet::new(RegexSet::new([r"https://b\.example"]).unwrap()).unwrap(),
+            ))
+            .allow_header(header::CONTENT_TYPE)
+            .allow_methods(AllowedMethods::Mirror)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merge_replace() {
+        let merged = base().merge_replace(overlay());
+        assert_eq!(
+            merged,
+            Config {
+                max_age: MaxAge::Seconds(Duration::from_secs(60)),
+                ..overlay()
+            }
+        );
+    }
+
+    #[test]
+    fn test_origins_or() {
+        let origins = AllowedOrigins::exact("https://a.example")
+            .or(AllowedOrigins::regex(r"https://.*\.b\.example").unwrap());
+        assert!(matches!(origins, AllowedOrigins::List(_)));
+        let matcher = origins.matcher();
+        assert!(matcher.is_match_str("https://a.example"));
+        assert!(matcher.is_match_str("https://api.b.example"));
+        assert!(!matcher.is_match_str("https://aXexample"));
+        assert!(!matcher.is_match_str("https://c.example"));
+
+        let origins = AllowedOrigins::Wildcard(HashSet::from(["https://*.c.example".to_string()]))
+            .or(AllowedOrigins::LocalhostAny);
+        assert!(origins.matcher().is_match_str("https://api.c.example"));
+        assert!(origins.matcher().is_match_str("http://localhost:3000"));
+
+        assert_eq!(
+            AllowedOrigins::exact("https://a.example")
+                .or(AllowedOrigins::exact("https://b.example")),
+            AllowedOrigins::Exact(HashSet::from([
+                "https://a.example".to_string(),
+                "https://b.example".to_string()
+            ]))
+        );
+        assert_eq!(
+            AllowedOrigins::exact("https://a.example").or(AllowedOrigins::Any),
+            AllowedOrigins::Any
+        );
+        assert_eq!(
+            AllowedOrigins::Mirror.or(AllowedOrigins::LocalhostAny),
+            AllowedOrigins::Mirror
+        );
+        assert!(matches!(
+            AllowedOrigins::Dynamic(OriginPredicate::new(|_, _| false))
+                .or(AllowedOrigins::exact("https://a.example")),
+            AllowedOrigins::Dynamic(_)
+        ));
+    }
+
+    #[test]
+    fn test_merge_union() {
+        let merged = base().merge_union(overlay());
+        assert!(merged.allow_credentials);
+        assert_eq!(
+            merged.allowed_origins,
+            AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://a\.example", r"https://b\.example"]).unwrap()
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            merged.allowed_headers,
+            AllowedHeaders::List(HashSet::from([header::AUTHORIZATION, header::CONTENT_TYPE]))
+        );
+        // mismatched kinds fall back to the override
+        assert_eq!(merged.allowed_methods, AllowedMethods::Mirror);
+        assert_eq!(merged.max_age, MaxAge::Seconds(Duration::from_secs(60)));
+    }
+}
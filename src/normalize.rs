@@ -0,0 +1,166 @@
+//! A canonical form for configs, so equivalent ones compare equal
+
+use std::collections::BTreeSet;
+
+use regex::RegexSet;
+
+use crate::{
+    matcher::to_ascii, AllowedHeaders, AllowedOrigins, Config, ExposeHeaders, OriginMaxAge,
+    SerdeRegexSet,
+};
+
+impl Config {
+    /// The same policy in a canonical form, for hashing or diffing configs
+    /// that were written differently.
+    ///
+    /// Header names are already lowercase, since `HeaderName` normalizes
+    /// them on construction. On top of that:
+    ///
+    /// - regex pattern lists, for origins and headers, are sorted and
+    ///   deduplicated, with the `^` and `$` anchors that matching adds anyway
+    ///   dropped.
+    /// - exact and wildcard origins are converted to their ASCII form, as
+    ///   they are for matching, when the `idna` feature is enabled.
+    /// - [`ExposeHeaders::SameAsAllowed`] is replaced by what it resolves
+    ///   to, and an empty exposed list becomes [`ExposeHeaders::None`].
+    ///
+    /// Nothing that changes which requests are allowed is touched: exact
+    /// origins stay case-sensitive, for instance.
+    pub fn normalized(self) -> Config {
+        let expose_headers = match self.resolved_expose_headers() {
+            ExposeHeaders::List(headers) if headers.is_empty() => ExposeHeaders::None,
+            expose_headers => expose_headers,
+        };
+        Config {
+            allowed_origins: self.allowed_origins.normalized(),
+            denied_origins: self
+                .denied_origins
+                .map(|denied| normalized_patterns(&denied)),
+            allowed_headers: match self.allowed_headers {
+                AllowedHeaders::Regex(patterns) => {
+                    AllowedHeaders::Regex(normalized_patterns(&patterns))
+                }
+                allowed_headers => allowed_headers,
+            },
+            max_age_by_origin: self
+                .max_age_by_origin
+                .into_iter()
+                .map(|group| OriginMaxAge {
+                    origins: group.origins.normalized(),
+                    max_age: group.max_age,
+                })
+                .collect(),
+            expose_headers,
+            ..self
+        }
+    }
+}
+
+impl AllowedOrigins {
+    fn normalized(self) -> AllowedOrigins {
+        match self {
+            AllowedOrigins::Exact(origins) => {
+                AllowedOrigins::Exact(origins.into_iter().map(to_ascii).collect())
+            }
+            AllowedOrigins::Wildcard(patterns) => {
+                AllowedOrigins::Wildcard(patterns.into_iter().map(to_ascii).collect())
+            }
+            AllowedOrigins::List(patterns) => AllowedOrigins::List(normalized_patterns(&patterns)),
+            origins => origins,
+        }
+    }
+}
+
+fn normalized_patterns(patterns: &SerdeRegexSet) -> SerdeRegexSet {
+    let stripped = patterns
+        .patterns()
+        .iter()
+        .map(|pattern| {
+            let stripped = crate::strip_anchors(pattern);
+            // dropping a `^` can break a pattern, e.g. `^*a`
+            if stripped.len() == pattern.len() || regex::Regex::new(stripped).is_ok() {
+                stripped
+            } else {
+                pattern
+            }
+        })
+        .collect::<BTreeSet<_>>();
+    RegexSet::new(stripped)
+        .and_then(SerdeRegexSet::new)
+        .unwrap_or_else(|_| patterns.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use http::HeaderName;
+
+    use super::*;
+
+    #[test]
+    fn test_normalized() {
+        let config = Config {
+            allowed_origins: AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([
+                        r"^https://b\.example$",
+                        r"https://a\.example",
+                        r"https://b\.example",
+                    ])
+                    .unwrap(),
+                )
+                .unwrap(),
+            ),
+            allowed_headers: AllowedHeaders::list([
+                HeaderName::from_bytes(b"X-Request-Id").unwrap()
+            ]),
+            expose_headers: ExposeHeaders::SameAsAllowed,
+            ..Config::default()
+        }
+        .normalized();
+        assert_eq!(
+            config.allowed_origins,
+            AllowedOrigins::List(
+                SerdeRegexSet::new(
+                    RegexSet::new([r"https://a\.example", r"https://b\.example"]).unwrap()
+                )
+                .unwrap()
+            )
+        );
+        let AllowedOrigins::List(patterns) = &config.allowed_origins else {
+            unreachable!()
+        };
+        assert_eq!(
+            patterns.patterns(),
+            [r"https://a\.example", r"https://b\.example"]
+        );
+
+        // `*a` doesn't compile, so the anchor stays
+        let origins = AllowedOrigins::regex("^*a$").unwrap();
+        let normalized = Config {
+            allowed_origins: origins.clone(),
+            ..Config::default()
+        }
+        .normalized();
+        assert_eq!(normalized.allowed_origins, origins);
+        assert_eq!(
+            config.expose_headers,
+            ExposeHeaders::List(HashSet::from([HeaderName::from_static("x-request-id")]))
+        );
+
+        // written differently, allowing the same
+        assert_eq!(
+            Config {
+                expose_headers: ExposeHeaders::List(HashSet::new()),
+                ..Config::default()
+            }
+            .normalized(),
+            Config {
+                expose_headers: ExposeHeaders::None,
+                ..Config::default()
+            }
+            .normalized()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! A validated `Origin` header value
+
+use std::{fmt, str::FromStr};
+
+use crate::ConfigError;
+
+/// A serialized origin as browsers send it in the `Origin` header:
+/// `scheme://host` with an optional `:port`, or the opaque origin `null`.
+///
+/// Entries of an [`AllowedOrigins::Exact`](crate::AllowedOrigins::Exact) list
+/// are checked against this when deserializing, so values that could never
+/// match such as `example.com` (no scheme) or `https://example.com/` (trailing
+/// slash) are rejected at load time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Origin(String);
+
+impl Origin {
+    /// Check that `origin` has the shape of an `Origin` header value
+    pub fn parse(origin: &str) -> Result<Origin, ConfigError> {
+        if origin == "null" || is_valid(origin) {
+            Ok(Origin(origin.to_string()))
+        } else {
+            Err(ConfigError::InvalidOrigin(origin.to_string()))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_valid(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let valid_scheme = scheme.starts_with(|char: char| char.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || matches!(char, '+' | '-' | '.'));
+    // IPv6 hosts are bracketed and contain colons of their own
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((host, port)) => (host, port),
+            None => return false,
+        },
+        None => authority.split_at(authority.rfind(':').unwrap_or(authority.len())),
+    };
+    let valid_host = !host.is_empty()
+        && !host.contains(|char: char| {
+            char.is_whitespace() || matches!(char, '/' | '?' | '#' | '@' | '[' | ']' | '\\')
+        })
+        && (authority.starts_with('[') || !host.contains(':'));
+    let valid_port = port.is_empty()
+        || port
+            .strip_prefix(':')
+            .is_some_and(|port| !port.is_empty() && port.parse::<u16>().is_ok());
+    valid_scheme && valid_host && valid_port
+}
+
+impl FromStr for Origin {
+    type Err = ConfigError;
+
+    fn from_str(origin: &str) -> Result<Origin, ConfigError> {
+        Origin::parse(origin)
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Origin {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Origin> for String {
+    fn from(origin: Origin) -> Self {
+        origin.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_origins() {
+        for origin in [
+            "https://example.com",
+            "http://localhost:3000",
+            "https://api.example.com:8443",
+            "http://127.0.0.1:8080",
+            "http://[::1]:8080",
+            "chrome-extension://abcdefghijklmnop",
+            "null",
+        ] {
+            assert_eq!(Origin::parse(origin).unwrap().as_str(), origin);
+        }
+    }
+
+    #[test]
+    fn test_invalid_origins() {
+        for origin in [
+            "example.com",
+            "https://example.com/",
+            "https://example.com/path",
+            "https://example.com?query",
+            "https://user@example.com",
+            "https://",
+            "https://example.com:",
+            "https://example.com:99999",
+            "https://[::1",
+            "://example.com",
+            "1http://example.com",
+            "https://exa mple.com",
+        ] {
+            assert!(
+                matches!(Origin::parse(origin), Err(ConfigError::InvalidOrigin(value)) if value == origin),
+                "{origin}"
+            );
+        }
+    }
+}
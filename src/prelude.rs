@@ -0,0 +1,16 @@
+//! The types most configs need, for a single glob import:
+//!
+//! ```
+//! use tower_http_cors_config::prelude::*;
+//!
+//! let config = ConfigBuilder::new()
+//!     .allow_origin(AllowedOrigins::exact("https://app.example.com"))
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(config.allowed_methods, AllowedMethods::none());
+//! ```
+
+pub use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigBuilder, ConfigError,
+    ExposeHeaders, HeaderCap, MaxAge, Vary,
+};
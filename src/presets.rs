@@ -0,0 +1,220 @@
+//! Ready-made configs mirroring tower-http's presets
+
+use std::collections::HashSet;
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders, MaxAge,
+    Origin, Vary, CONFIG_VERSION, STANDARD_METHODS,
+};
+
+impl Config {
+    /// A permissive config, equivalent to [`CorsLayer::permissive`]:
+    ///
+    /// - All request headers allowed.
+    /// - All methods allowed.
+    /// - All origins allowed.
+    /// - All headers exposed.
+    ///
+    /// Unlike tower-http, no `Vary` header is sent, since the response doesn't
+    /// depend on the request.
+    ///
+    /// [`CorsLayer::permissive`]: tower_http::cors::CorsLayer::permissive
+    pub fn permissive() -> Config {
+        Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: false,
+            allowed_headers: AllowedHeaders::Any,
+            allowed_methods: AllowedMethods::Any,
+            allowed_origins: AllowedOrigins::Any,
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::Any,
+            // every response is the same `*`, so there is nothing to vary on
+            vary: Vary::default(),
+            on_origin_decision: None,
+        }
+    }
+
+    /// A very permissive config, equivalent to
+    /// [`CorsLayer::very_permissive`]:
+    ///
+    /// - **Credentials allowed.**
+    /// - The requested method, origin and headers are mirrored back.
+    /// - No headers are exposed.
+    ///
+    /// [`CorsLayer::very_permissive`]: tower_http::cors::CorsLayer::very_permissive
+    pub fn very_permissive() -> Config {
+        Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::Mirror,
+            allowed_methods: AllowedMethods::Mirror,
+            allowed_origins: AllowedOrigins::Mirror,
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::List(Default::default()),
+            vary: Vary(tower_http::cors::preflight_request_headers().collect()),
+            on_origin_decision: None,
+        }
+    }
+
+    /// A config that disables cross-origin access entirely, the counterpart
+    /// to [`Config::permissive`]:
+    ///
+    /// - No origins allowed, so every preflight is rejected.
+    /// - No methods or request headers allowed.
+    /// - No headers exposed and no credentials.
+    ///
+    /// [`Config::validate`] warns about each empty list, which is exactly
+    /// what this config is for.
+    pub fn deny_all() -> Config {
+        Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: false,
+            allowed_headers: AllowedHeaders::none(),
+            allowed_methods: AllowedMethods::none(),
+            allowed_origins: AllowedOrigins::none(),
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::none(),
+            vary: Vary::default(),
+            on_origin_decision: None,
+        }
+    }
+
+    /// The usual setup for a browser app that logs in to an API on another
+    /// origin, failing on the first entry of `origins` that isn't a valid
+    /// [`Origin`]:
+    ///
+    /// - **Credentials allowed**, for exactly `origins`. Each allowed origin
+    ///   is echoed back instead of `*`, which credentials require.
+    /// - `Authorization` and `Content-Type` allowed as request headers.
+    /// - The [`STANDARD_METHODS`] allowed.
+    /// - `Vary: Origin`, since responses differ by origin.
+    /// - No headers exposed and no `max-age`.
+    ///
+    /// ```
+    /// use tower_http::cors::CorsLayer;
+    /// use tower_http_cors_config::Config;
+    ///
+    /// let config = Config::credentialed(&["https://app.example.com"]).unwrap();
+    /// let layer = CorsLayer::try_from(config).unwrap();
+    /// ```
+    pub fn credentialed(origins: &[&str]) -> Result<Config, ConfigError> {
+        let origins = origins
+            .iter()
+            .map(|origin| Origin::parse(origin).map(String::from))
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(Config {
+            enabled: true,
+            version: CONFIG_VERSION,
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::list([
+                http::header::AUTHORIZATION,
+                http::header::CONTENT_TYPE,
+            ]),
+            allowed_methods: AllowedMethods::list(STANDARD_METHODS),
+            allowed_origins: AllowedOrigins::Exact(origins),
+            denied_origins: None,
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            max_age_by_origin: Vec::new(),
+            expose_headers: ExposeHeaders::none(),
+            vary: Vary(HashSet::from([http::header::ORIGIN])),
+            on_origin_decision: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_http::cors::CorsLayer;
+
+    use super::*;
+    use crate::tests::{cors_headers, preflight_from, request_from};
+
+    #[test]
+    fn test_presets_build_usable_layers() {
+        let headers = cors_headers(
+            CorsLayer::try_from(Config::permissive()).unwrap(),
+            request_from("https://example.com"),
+        );
+        assert_eq!(headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(headers[http::header::ACCESS_CONTROL_EXPOSE_HEADERS], "*");
+
+        let headers = cors_headers(
+            CorsLayer::try_from(Config::very_permissive()).unwrap(),
+            request_from("https://example.com"),
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+    }
+
+    #[test]
+    fn test_deny_all_rejects_every_origin() {
+        let layer = CorsLayer::try_from(Config::deny_all()).unwrap();
+        for origin in ["https://example.com", "http://localhost:3000", "null"] {
+            for request in [request_from(origin), preflight_from(origin)] {
+                let headers = cors_headers(layer.clone(), request);
+                assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+            }
+        }
+    }
+
+    #[test]
+    fn test_credentialed() {
+        let config = Config::credentialed(&["https://a.example", "https://b.example"]).unwrap();
+        assert!(config.errors().is_empty());
+        assert!(config.warnings().is_empty());
+        let layer = CorsLayer::try_from(config).unwrap();
+        for origin in ["https://a.example", "https://b.example"] {
+            for request in [request_from(origin), preflight_from(origin)] {
+                // would panic on `*` with credentials
+                let headers = cors_headers(layer.clone(), request);
+                assert_eq!(headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+                assert_eq!(
+                    headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+                    "true"
+                );
+            }
+        }
+        let headers = cors_headers(layer, request_from("https://c.example"));
+        assert!(!headers.contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        assert!(matches!(
+            Config::credentialed(&["a.example"]),
+            Err(ConfigError::InvalidOrigin(origin)) if origin == "a.example"
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_presets_roundtrip() {
+        use pretty_assertions::assert_eq;
+
+        for config in [
+            Config::permissive(),
+            Config::very_permissive(),
+            Config::deny_all(),
+            Config::credentialed(&["https://a.example"]).unwrap(),
+        ] {
+            let serialized = serde_yaml::to_string(&config).unwrap();
+            assert_eq!(serde_yaml::from_str::<Config>(&serialized).unwrap(), config);
+        }
+    }
+}
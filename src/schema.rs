@@ -0,0 +1,87 @@
+//! Stand-in types describing the JSON Schema of fields with custom serde
+//! representations
+
+use std::collections::HashSet;
+
+use schemars::JsonSchema;
+
+/// Origins compared as exact strings
+#[derive(JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExactOrigins {
+    #[allow(dead_code)]
+    exact: HashSet<String>,
+}
+
+/// Origins matched with `*` wildcards, each matching a single DNS label
+#[derive(JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WildcardOrigins {
+    #[allow(dead_code)]
+    wildcard: HashSet<String>,
+}
+
+/// Request headers mirrored back only if they match one of these regexes
+#[derive(JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RegexHeaders {
+    #[allow(dead_code)]
+    regex: HashSet<String>,
+}
+
+/// Request headers mirrored back unless there are too many of them
+#[derive(JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorCappedHeaders {
+    #[allow(dead_code)]
+    #[serde(rename = "mirror-capped")]
+    mirror_capped: crate::HeaderCap,
+}
+
+/// Request headers mirrored back only if they are in this list
+#[derive(JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorListHeaders {
+    #[allow(dead_code)]
+    #[serde(rename = "mirror-list")]
+    mirror_list: HashSet<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Config;
+
+    #[test]
+    fn test_config_schema() {
+        let schema = schemars::schema_for!(Config);
+        let properties = schema.get("properties").unwrap().as_object().unwrap();
+        for field in [
+            "enabled",
+            "allow-credentials",
+            "allowed-headers",
+            "allowed-methods",
+            "allowed-origins",
+            "allow-private-network",
+            "max-age",
+            "expose-headers",
+            "vary",
+        ] {
+            assert!(properties.contains_key(field), "missing {field}");
+        }
+        let definitions = schema.get("$defs").unwrap().as_object().unwrap();
+        for definition in [
+            "AllowedOrigins",
+            "ExactOrigins",
+            "WildcardOrigins",
+            "MirrorCappedHeaders",
+            "MirrorListHeaders",
+            "RegexHeaders",
+            "SerdeRegexSet",
+        ] {
+            assert!(definitions.contains_key(definition), "missing {definition}");
+        }
+        // untagged list variants are plain arrays rather than `{ list: [...] }`
+        assert_eq!(definitions["SerdeRegexSet"]["type"], "array");
+        assert_eq!(definitions["AllowedHeaders"]["anyOf"][5]["type"], "array");
+    }
+}
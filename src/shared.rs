@@ -0,0 +1,166 @@
+//! An origin allowlist that can be replaced while layers are using it
+
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use regex::RegexSet;
+
+use crate::{ConfigError, SerdeRegexSet};
+
+/// A regex origin allowlist shared between a [`CorsLayer`] and whatever
+/// updates it, e.g. a management endpoint, without rebuilding the layer.
+///
+/// Use it through [`AllowedOrigins::Shared`]. Patterns behave like
+/// [`AllowedOrigins::List`]: each must match the entire origin. Clones share
+/// the same list, so keep one to call [`SharedOrigins::replace`] on.
+///
+/// Every origin check reads the list current at that moment:
+///
+/// - a replacement is atomic, so a check sees either the whole old list or
+///   the whole new one, never a mix.
+/// - checks already running when the list is replaced finish with the old
+///   list; only later ones see the new one.
+/// - a preflight and the request that follows are checked separately and
+///   may see different lists.
+/// - browsers keep cached preflight responses for up to `max-age`, so
+///   removing an origin only takes effect there once they expire.
+///
+/// ```
+/// use regex::RegexSet;
+/// use tower_http::cors::CorsLayer;
+/// use tower_http_cors_config::{AllowedOrigins, Config, SharedOrigins};
+///
+/// let origins = SharedOrigins::new(RegexSet::new([r"https://app\.example"]).unwrap()).unwrap();
+/// let layer = CorsLayer::try_from(Config {
+///     allowed_origins: AllowedOrigins::Shared(origins.clone()),
+///     ..Config::default()
+/// })
+/// .unwrap();
+///
+/// // later, e.g. from an admin handler
+/// origins
+///     .replace(RegexSet::new([r"https://app\.example", r"https://beta\.example"]).unwrap())
+///     .unwrap();
+/// # let _ = layer;
+/// ```
+///
+/// [`CorsLayer`]: tower_http::cors::CorsLayer
+/// [`AllowedOrigins::Shared`]: crate::AllowedOrigins::Shared
+/// [`AllowedOrigins::List`]: crate::AllowedOrigins::List
+#[derive(Clone)]
+pub struct SharedOrigins(Arc<RwLock<Patterns>>);
+
+struct Patterns {
+    patterns: SerdeRegexSet,
+    anchored: RegexSet,
+}
+
+impl Patterns {
+    fn new(patterns: RegexSet) -> Result<Self, ConfigError> {
+        let patterns = SerdeRegexSet::new(patterns)?;
+        let anchored = patterns.anchored();
+        Ok(Patterns { patterns, anchored })
+    }
+}
+
+impl SharedOrigins {
+    /// Start out allowing origins matching `patterns`, or fail if one of
+    /// them doesn't compile once anchored
+    pub fn new(patterns: RegexSet) -> Result<Self, ConfigError> {
+        Ok(SharedOrigins(Arc::new(RwLock::new(Patterns::new(
+            patterns,
+        )?))))
+    }
+
+    /// Allow origins matching `patterns` from now on, instead of the
+    /// current list. If one of them doesn't compile once anchored, the
+    /// current list stays and the error is returned.
+    pub fn replace(&self, patterns: RegexSet) -> Result<(), ConfigError> {
+        // compile outside the lock so checks aren't held up
+        let patterns = Patterns::new(patterns)?;
+        *self.0.write().unwrap_or_else(|error| error.into_inner()) = patterns;
+        Ok(())
+    }
+
+    /// A copy of the current list
+    pub fn patterns(&self) -> SerdeRegexSet {
+        self.read(|patterns| patterns.patterns.clone())
+    }
+
+    /// Whether the current list allows `origin`
+    pub fn is_match(&self, origin: &str) -> bool {
+        self.read(|patterns| patterns.anchored.is_match(origin))
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Patterns) -> T) -> T {
+        // a writer can't panic halfway through an assignment, so the list
+        // is still whole if the lock is poisoned
+        f(&self.0.read().unwrap_or_else(|error| error.into_inner()))
+    }
+}
+
+impl fmt::Debug for SharedOrigins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedOrigins")
+            .field(&self.patterns().patterns())
+            .finish()
+    }
+}
+
+/// Handles are equal only if they share the same list
+impl PartialEq for SharedOrigins {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedOrigins {}
+
+impl std::hash::Hash for SharedOrigins {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace() {
+        let origins = SharedOrigins::new(RegexSet::new([r"https://a\.example"]).unwrap()).unwrap();
+        let clone = origins.clone();
+        assert!(clone.is_match("https://a.example"));
+        assert!(!clone.is_match("https://b.example"));
+        // still anchored like a regex list
+        assert!(!clone.is_match("https://a.example.evil"));
+
+        origins
+            .replace(RegexSet::new([r"https://b\.example"]).unwrap())
+            .unwrap();
+        assert!(!clone.is_match("https://a.example"));
+        assert!(clone.is_match("https://b.example"));
+        assert_eq!(
+            clone.patterns(),
+            SerdeRegexSet::new(RegexSet::new([r"https://b\.example"]).unwrap()).unwrap()
+        );
+        assert_eq!(origins, clone);
+        assert_ne!(origins, SharedOrigins::new(RegexSet::empty()).unwrap());
+    }
+
+    #[test]
+    fn test_replace_keeps_the_list_on_error() {
+        let origins = SharedOrigins::new(RegexSet::new([r"https://a\.example"]).unwrap()).unwrap();
+        // valid, but the comment swallows the anchoring group's `)$`
+        let patterns = RegexSet::new([r"(?x)https://b\.example # beta"]).unwrap();
+        assert!(matches!(
+            origins.replace(patterns.clone()),
+            Err(ConfigError::InvalidRegex(_))
+        ));
+        assert!(origins.is_match("https://a.example"));
+        assert!(!origins.is_match("https://b.example"));
+        assert!(SharedOrigins::new(patterns).is_err());
+    }
+}
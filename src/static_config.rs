@@ -0,0 +1,166 @@
+//! A `const`-constructible config for policies embedded in the binary
+
+use std::collections::HashSet;
+
+use http::{HeaderName, Method};
+
+use crate::{
+    AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders, MaxAge,
+    Origin,
+};
+
+/// A [`Config`] made of `'static` slices, so a baseline policy can live in a
+/// `static` or `const` without lazy initialization.
+///
+/// [`Config`] itself can't be built in const contexts, since `HashSet` and
+/// `RegexSet` allocate. This type covers the common subset: exact origins
+/// and plain lists. Convert it with [`StaticConfig::to_config`] (or
+/// `Config::try_from`) when the layer is built, which also validates the
+/// entries.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use tower_http::cors::CorsLayer;
+/// use tower_http_cors_config::{MaxAge, StaticConfig};
+///
+/// static CORS: StaticConfig = StaticConfig {
+///     allowed_origins: &["https://app.example.com"],
+///     allowed_methods: &["GET", "POST"],
+///     allowed_headers: &["content-type"],
+///     max_age: MaxAge::Seconds(Duration::from_secs(600)),
+///     ..StaticConfig::deny_all()
+/// };
+///
+/// let layer = CorsLayer::try_from(CORS.to_config().unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StaticConfig {
+    pub allow_credentials: bool,
+    /// Origins compared exactly, as with [`AllowedOrigins::Exact`]
+    pub allowed_origins: &'static [&'static str],
+    /// Method names, case-sensitive like [`Method::from_bytes`]
+    pub allowed_methods: &'static [&'static str],
+    pub allowed_headers: &'static [&'static str],
+    pub allow_private_network: bool,
+    pub max_age: MaxAge,
+    pub expose_headers: &'static [&'static str],
+}
+
+impl StaticConfig {
+    /// The same policy as [`Config::deny_all`], as a starting point for
+    /// struct update syntax
+    pub const fn deny_all() -> StaticConfig {
+        StaticConfig {
+            allow_credentials: false,
+            allowed_origins: &[],
+            allowed_methods: &[],
+            allowed_headers: &[],
+            allow_private_network: false,
+            max_age: MaxAge::Disabled,
+            expose_headers: &[],
+        }
+    }
+
+    /// Build the equivalent [`Config`], failing on the first invalid origin,
+    /// method or header name
+    pub fn to_config(&self) -> Result<Config, ConfigError> {
+        let origins = self
+            .allowed_origins
+            .iter()
+            .map(|origin| Origin::parse(origin).map(String::from))
+            .collect::<Result<HashSet<_>, _>>()?;
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.as_bytes())
+                    .map_err(|_| ConfigError::InvalidMethod(method.to_string()))
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(Config {
+            allow_credentials: self.allow_credentials,
+            allowed_headers: AllowedHeaders::List(header_names(self.allowed_headers)?),
+            allowed_methods: AllowedMethods::List(methods),
+            allowed_origins: AllowedOrigins::Exact(origins),
+            allow_private_network: self.allow_private_network,
+            max_age: self.max_age,
+            expose_headers: ExposeHeaders::List(header_names(self.expose_headers)?),
+            ..Config::deny_all()
+        })
+    }
+}
+
+fn header_names(headers: &[&str]) -> Result<HashSet<HeaderName>, ConfigError> {
+    headers
+        .iter()
+        .map(|header| {
+            HeaderName::try_from(*header)
+                .map_err(|_| ConfigError::InvalidHeaderName(header.to_string()))
+        })
+        .collect()
+}
+
+impl TryFrom<StaticConfig> for Config {
+    type Error = ConfigError;
+
+    fn try_from(value: StaticConfig) -> Result<Self, Self::Error> {
+        value.to_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DENY_ALL: StaticConfig = StaticConfig::deny_all();
+
+    #[test]
+    fn test_static_config() {
+        static CONFIG: StaticConfig = StaticConfig {
+            allow_credentials: true,
+            allowed_origins: &["https://a.example", "https://b.example:8443"],
+            allowed_methods: &["GET", "PURGE"],
+            allowed_headers: &["Content-Type"],
+            expose_headers: &["etag"],
+            ..DENY_ALL
+        };
+        assert_eq!(
+            Config::try_from(CONFIG).unwrap(),
+            Config {
+                allow_credentials: true,
+                allowed_origins: AllowedOrigins::Exact(HashSet::from([
+                    "https://a.example".to_string(),
+                    "https://b.example:8443".to_string()
+                ])),
+                allowed_methods: AllowedMethods::list([
+                    Method::GET,
+                    Method::from_bytes(b"PURGE").unwrap()
+                ]),
+                allowed_headers: AllowedHeaders::list([http::header::CONTENT_TYPE]),
+                expose_headers: ExposeHeaders::list([http::header::ETAG]),
+                ..Config::deny_all()
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        let config = StaticConfig {
+            allowed_origins: &["https://example.com/"],
+            ..DENY_ALL
+        };
+        assert!(matches!(
+            config.to_config(),
+            Err(ConfigError::InvalidOrigin(_))
+        ));
+        let config = StaticConfig {
+            allowed_headers: &["not a header"],
+            ..DENY_ALL
+        };
+        assert!(matches!(
+            config.to_config(),
+            Err(ConfigError::InvalidHeaderName(_))
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+//! Opt-in stricter deserialization
+
+use std::cell::Cell;
+
+use serde::{Deserialize, Deserializer};
+
+thread_local! {
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Deserializes `T` with stricter checks than the lenient defaults.
+///
+/// Currently this rejects header lists that name the same header twice,
+/// ignoring case, which usually points to a copy-paste mistake in a long
+/// allowlist. Without `Strict`, duplicates silently collapse into one entry.
+///
+/// ```
+/// # use tower_http_cors_config::{Config, Strict};
+/// let yaml = "
+/// allowed-origins: any
+/// allowed-methods: [GET]
+/// allowed-headers: [x-request-id, X-Request-Id]
+/// expose-headers: []
+/// ";
+/// assert!(serde_yaml::from_str::<Config>(yaml).is_ok());
+/// assert!(serde_yaml::from_str::<Strict<Config>>(yaml).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strict<T>(pub T);
+
+impl<'de, T> Deserialize<'de> for Strict<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Reset(bool);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                STRICT.set(self.0);
+            }
+        }
+
+        let _reset = Reset(STRICT.replace(true));
+        T::deserialize(deserializer).map(Strict)
+    }
+}
+
+/// Whether we are inside a [`Strict`] deserialization
+pub(crate) fn is_strict() -> bool {
+    STRICT.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigError, Vary};
+
+    #[test]
+    fn test_duplicate_headers() {
+        let yaml = "[origin, Origin]";
+        assert_eq!(
+            serde_yaml::from_str::<Vary>(yaml).unwrap(),
+            Vary([http::header::ORIGIN].into())
+        );
+        let error = serde_yaml::from_str::<Strict<Vary>>(yaml).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&ConfigError::DuplicateHeaderName("Origin".to_string()).to_string()));
+        // strictness doesn't leak into later deserializations
+        assert!(serde_yaml::from_str::<Vary>(yaml).is_ok());
+    }
+}
@@ -0,0 +1,374 @@
+//! Helpers for asserting how a `CorsLayer` or `ConfigLayer` behaves without
+//! running a server
+
+use std::{
+    convert::Infallible,
+    future::{ready, Future, Ready},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{Config, ConfigError, ConfigLayer};
+
+/// The CORS response headers a layer sent for one request.
+///
+/// tower-http keeps the settings of a `CorsLayer` private, so this observes
+/// them from the outside instead. That works the same for layers built from a
+/// [`Config`](crate::Config) and by hand, which makes it possible to check
+/// that the two agree, and for a [`ConfigLayer`] or any other layer in front
+/// of [`AlwaysOk`]. Header values are kept as sent, unparsed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObservedCors {
+    /// `Access-Control-Allow-Origin`
+    pub allow_origin: Option<String>,
+    /// Whether `Access-Control-Allow-Credentials: true` was sent
+    pub allow_credentials: bool,
+    /// `Access-Control-Allow-Methods`
+    pub allow_methods: Option<String>,
+    /// `Access-Control-Allow-Headers`
+    pub allow_headers: Option<String>,
+    /// Whether `Access-Control-Allow-Private-Network: true` was sent
+    pub allow_private_network: bool,
+    /// `Access-Control-Expose-Headers`
+    pub expose_headers: Option<String>,
+    /// `Access-Control-Max-Age`
+    pub max_age: Option<String>,
+    /// Every `Vary` header, in order
+    pub vary: Vec<String>,
+}
+
+impl ObservedCors {
+    /// Run `request` through `layer` in front of a service that always
+    /// responds with `200 OK`, and record the CORS headers of the response
+    pub fn observe<L>(layer: &L, request: Request<()>) -> ObservedCors
+    where
+        L: Layer<AlwaysOk>,
+        L::Service: Service<Request<()>, Response = Response<()>, Error = Infallible>,
+    {
+        ObservedCors::from_headers(&run(layer, request))
+    }
+
+    /// Observe the response to a preflight from `origin` asking to send
+    /// `method` with `request_headers`. Fails if `origin` can't be sent as a
+    /// header value.
+    pub fn preflight<L>(
+        layer: &L,
+        origin: &str,
+        method: Method,
+        request_headers: &[HeaderName],
+    ) -> Result<ObservedCors, ConfigError>
+    where
+        L: Layer<AlwaysOk>,
+        L::Service: Service<Request<()>, Response = Response<()>, Error = Infallible>,
+    {
+        let origin = HeaderValue::from_str(origin)
+            .map_err(|_| ConfigError::InvalidOrigin(origin.to_string()))?;
+        let mut request = Request::options("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method.as_str());
+        if !request_headers.is_empty() {
+            let names = request_headers
+                .iter()
+                .map(HeaderName::as_str)
+                .collect::<Vec<_>>()
+                .join(",");
+            request = request.header(header::ACCESS_CONTROL_REQUEST_HEADERS, names);
+        }
+        let request = request
+            .body(())
+            .expect("header names joined by commas are a valid header value");
+        Ok(ObservedCors::observe(layer, request))
+    }
+
+    fn from_headers(headers: &HeaderMap) -> ObservedCors {
+        let value = |name| {
+            headers
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        };
+        let is_true = |name| headers.get(name).is_some_and(|value| value == "true");
+        ObservedCors {
+            allow_origin: value(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            allow_credentials: is_true(header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            allow_methods: value(header::ACCESS_CONTROL_ALLOW_METHODS),
+            allow_headers: value(header::ACCESS_CONTROL_ALLOW_HEADERS),
+            allow_private_network: is_true(HeaderName::from_static(
+                "access-control-allow-private-network",
+            )),
+            expose_headers: value(header::ACCESS_CONTROL_EXPOSE_HEADERS),
+            max_age: value(header::ACCESS_CONTROL_MAX_AGE),
+            vary: headers
+                .get_all(header::VARY)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .collect(),
+        }
+    }
+}
+
+/// The outcome of [`Config::simulate_preflight`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightResult {
+    /// `Access-Control-Allow-Origin`
+    pub allow_origin: Option<String>,
+    /// `Access-Control-Allow-Methods`
+    pub allow_methods: Option<String>,
+    /// `Access-Control-Allow-Headers`
+    pub allow_headers: Option<String>,
+    /// `Access-Control-Max-Age`, if it was sent and is a number of seconds
+    pub max_age: Option<Duration>,
+    /// Whether a browser would go on to send the actual request
+    pub allowed: bool,
+    /// Every CORS header of the response, including the ones above
+    pub observed: ObservedCors,
+}
+
+impl Config {
+    /// Build a [`ConfigLayer`] and send it a preflight from `origin` asking to
+    /// send `method` with `headers`, as a browser would.
+    ///
+    /// [`PreflightResult::allowed`] follows the browser's checks: the origin
+    /// must be echoed back or be `*` (never with credentials), and `method`
+    /// and every header must be listed or covered by `*`, except that
+    /// `GET`, `HEAD` and `POST` never need to be listed and `*` doesn't cover
+    /// `Authorization`. Fails if the config can't be turned into a layer, or
+    /// `origin` can't be sent as a header value.
+    pub fn simulate_preflight(
+        &self,
+        origin: &str,
+        method: Method,
+        headers: &[HeaderName],
+    ) -> Result<PreflightResult, ConfigError> {
+        let layer = ConfigLayer::try_from(self.clone())?;
+        let observed = ObservedCors::preflight(&layer, origin, method.clone(), headers)?;
+
+        let credentials = observed.allow_credentials;
+        let listed = |list: &Option<String>, name: &str| {
+            list.as_deref().is_some_and(|list| {
+                list.split(',')
+                    .map(str::trim)
+                    .any(|entry| entry.eq_ignore_ascii_case(name) || (entry == "*" && !credentials))
+            })
+        };
+        let origin_allowed = match observed.allow_origin.as_deref() {
+            Some("*") => !credentials,
+            Some(allowed) => allowed == origin,
+            None => false,
+        };
+        let method_allowed = [Method::GET, Method::HEAD, Method::POST].contains(&method)
+            || listed(&observed.allow_methods, method.as_str());
+        let headers_allowed = headers.iter().all(|header| {
+            if header == header::AUTHORIZATION {
+                observed.allow_headers.as_deref().is_some_and(|list| {
+                    list.split(',')
+                        .any(|entry| entry.trim().eq_ignore_ascii_case(header.as_str()))
+                })
+            } else {
+                listed(&observed.allow_headers, header.as_str())
+            }
+        });
+
+        Ok(PreflightResult {
+            allow_origin: observed.allow_origin.clone(),
+            allow_methods: observed.allow_methods.clone(),
+            allow_headers: observed.allow_headers.clone(),
+            max_age: observed
+                .max_age
+                .as_deref()
+                .and_then(|secs| secs.parse().ok())
+                .map(Duration::from_secs),
+            allowed: origin_allowed && method_allowed && headers_allowed,
+            observed,
+        })
+    }
+}
+
+/// The service [`ObservedCors`] runs layers in front of, which always
+/// responds with an empty `200 OK`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysOk;
+
+impl Service<Request<()>> for AlwaysOk {
+    type Response = Response<()>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _request: Request<()>) -> Self::Future {
+        ready(Ok(Response::new(())))
+    }
+}
+
+/// Poll `request` through `layer` once; nothing involved ever waits
+fn run<L>(layer: &L, request: Request<()>) -> HeaderMap
+where
+    L: Layer<AlwaysOk>,
+    L::Service: Service<Request<()>, Response = Response<()>, Error = Infallible>,
+{
+    let mut service = layer.layer(AlwaysOk);
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(service.poll_ready(&mut cx).is_ready());
+    match std::pin::pin!(service.call(request)).poll(&mut cx) {
+        Poll::Ready(Ok(response)) => response.headers().clone(),
+        Poll::Ready(Err(error)) => match error {},
+        Poll::Pending => unreachable!("the inner service is always ready"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use regex::RegexSet;
+    use tower_http::cors::CorsLayer;
+
+    use super::*;
+    use crate::{
+        AllowedHeaders, AllowedMethods, AllowedOrigins, Config, HeaderCap, MaxAge, SerdeRegexSet,
+    };
+
+    #[test]
+    fn test_config_layer_matches_hand_built_layer() {
+        let config = Config {
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::List(HashSet::from([header::AUTHORIZATION])),
+            allowed_methods: AllowedMethods::List(HashSet::from([Method::GET])),
+            allowed_origins: AllowedOrigins::Mirror,
+            max_age: MaxAge::Seconds(Duration::from_secs(600)),
+            ..Config::default()
+        };
+        let from_config = CorsLayer::try_from(config).unwrap();
+        let by_hand = CorsLayer::new()
+            .allow_credentials(true)
+            .allow_headers([header::AUTHORIZATION])
+            .allow_methods([Method::GET])
+            .allow_origin(tower_http::cors::AllowOrigin::mirror_request())
+            .max_age(Duration::from_secs(600))
+            .vary([]);
+
+        let observe = |layer| {
+            ObservedCors::preflight(
+                layer,
+                "https://example.com",
+                Method::GET,
+                &[header::AUTHORIZATION],
+            )
+            .unwrap()
+        };
+        let observed = observe(&from_config);
+        assert_eq!(observed, observe(&by_hand));
+        assert_eq!(
+            observed,
+            ObservedCors {
+                allow_origin: Some("https://example.com".to_string()),
+                allow_credentials: true,
+                allow_methods: Some("GET".to_string()),
+                allow_headers: Some("authorization".to_string()),
+                max_age: Some("600".to_string()),
+                ..ObservedCors::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_simulate_preflight() {
+        let config = Config {
+            allowed_headers: AllowedHeaders::List(HashSet::from([header::CONTENT_TYPE])),
+            allowed_methods: AllowedMethods::List(HashSet::from([Method::GET, Method::PUT])),
+            allowed_origins: AllowedOrigins::exact("https://app.example"),
+            max_age: MaxAge::Seconds(Duration::from_secs(60)),
+            ..Config::default()
+        };
+        let result = config
+            .simulate_preflight("https://app.example", Method::PUT, &[header::CONTENT_TYPE])
+            .unwrap();
+        assert!(result.allowed);
+        assert_eq!(result.allow_origin.as_deref(), Some("https://app.example"));
+        assert_eq!(result.max_age, Some(Duration::from_secs(60)));
+
+        let allowed = |origin, method, headers: &[HeaderName]| {
+            config
+                .simulate_preflight(origin, method, headers)
+                .unwrap()
+                .allowed
+        };
+        assert!(!allowed("https://evil.example", Method::GET, &[]));
+        assert!(!allowed("https://app.example", Method::DELETE, &[]));
+        assert!(!allowed(
+            "https://app.example",
+            Method::GET,
+            &[header::AUTHORIZATION]
+        ));
+
+        let any = Config::permissive();
+        assert!(
+            any.simulate_preflight("https://x.example", Method::DELETE, &[header::RANGE])
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !any.simulate_preflight("https://x.example", Method::GET, &[header::AUTHORIZATION])
+                .unwrap()
+                .allowed
+        );
+
+        let invalid = Config {
+            allow_credentials: true,
+            ..Config::permissive()
+        };
+        assert!(invalid
+            .simulate_preflight("https://x.example", Method::GET, &[])
+            .is_err());
+
+        assert!(matches!(
+            config.simulate_preflight("https://app.example\n", Method::GET, &[]),
+            Err(ConfigError::InvalidOrigin(_))
+        ));
+    }
+
+    #[test]
+    fn test_simulate_preflight_with_config_layer_headers() {
+        let x_trace = || HeaderName::from_static("x-myapp-trace");
+        let regex = Config {
+            allowed_headers: AllowedHeaders::Regex(
+                SerdeRegexSet::new(RegexSet::new(["x-myapp-.*"]).unwrap()).unwrap(),
+            ),
+            allowed_origins: AllowedOrigins::Any,
+            ..Config::default()
+        };
+        let result = regex
+            .simulate_preflight("https://x.example", Method::GET, &[x_trace()])
+            .unwrap();
+        assert!(result.allowed);
+        assert_eq!(result.allow_headers.as_deref(), Some("x-myapp-trace"));
+        assert!(
+            !regex
+                .simulate_preflight("https://x.example", Method::GET, &[header::RANGE])
+                .unwrap()
+                .allowed
+        );
+
+        let capped = Config {
+            allowed_headers: AllowedHeaders::MirrorCapped(HeaderCap {
+                max_headers: 1,
+                ..HeaderCap::default()
+            }),
+            ..regex
+        };
+        let allowed = |headers: &[HeaderName]| {
+            capped
+                .simulate_preflight("https://x.example", Method::GET, headers)
+                .unwrap()
+                .allowed
+        };
+        assert!(allowed(&[x_trace()]));
+        assert!(!allowed(&[x_trace(), header::RANGE]));
+    }
+}
@@ -0,0 +1,643 @@
+//! Checks for configs that parse fine but are likely mistakes
+
+use std::time::Duration;
+
+use http::Method;
+use tower_http::cors::CorsLayer;
+
+use crate::{AllowedHeaders, AllowedMethods, AllowedOrigins, Config, ConfigError, ExposeHeaders};
+
+/// The methods accepted by [`Config::validate_standard_methods`]
+pub const STANDARD_METHODS: [Method; 7] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::PATCH,
+    Method::HEAD,
+    Method::OPTIONS,
+];
+
+/// The longest preflight cache any major browser honours: Firefox caps
+/// `Access-Control-Max-Age` at 24 hours, Chromium at 2 hours.
+///
+/// A longer `max-age` isn't an error, but browsers silently clamp it, so
+/// [`Config::warnings`] reports it.
+pub const MAX_BROWSER_MAX_AGE: Duration = Duration::from_secs(86400);
+
+/// How seriously to take a [`ConfigError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Probably a mistake, but the config still builds a working layer
+    Warning,
+    /// The config can't be used as-is
+    Error,
+}
+
+/// Whether warnings should fail [`Config::try_into_layer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Only errors fail; warnings are left for [`Config::warnings`] to report
+    #[default]
+    Lenient,
+    /// Warnings fail just like errors
+    Strict,
+}
+
+impl Config {
+    /// Build a [`CorsLayer`], optionally treating warnings as errors.
+    ///
+    /// `CorsLayer::try_from(config)` is the same as
+    /// `config.try_into_layer(Strictness::Lenient)`.
+    pub fn try_into_layer(self, strictness: Strictness) -> Result<CorsLayer, ConfigError> {
+        if strictness == Strictness::Strict {
+            if let Some(warning) = self.warnings().into_iter().next() {
+                return Err(warning);
+            }
+        }
+        CorsLayer::try_from(self)
+    }
+
+    /// Check the config for every problem at once, both errors and
+    /// [warnings](Config::warnings).
+    ///
+    /// Each problem's [`ConfigError::severity`] tells whether it would stop
+    /// `CorsLayer::try_from` from succeeding. This is meant for tools that
+    /// want to report everything before exiting; `CorsLayer::try_from` stops
+    /// at the first error instead.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut problems = self.errors();
+        problems.extend(self.warnings());
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Like [`Config::validate`], but log every problem through the `log`
+    /// crate and only fail on errors.
+    ///
+    /// Warnings go to `log::warn!` and errors to `log::error!`, so one call at
+    /// startup is enough to get diagnostics in the application's log. Only
+    /// the errors are returned, since they are what keeps the layer from
+    /// building.
+    ///
+    /// This is independent of the `tracing` feature, which logs origin
+    /// decisions per request; the two can be enabled together. `tracing`
+    /// users can forward these records with `tracing-log`.
+    #[cfg(feature = "log")]
+    pub fn validate_and_log(&self) -> Result<(), Vec<ConfigError>> {
+        let errors = self.errors();
+        for warning in self.warnings() {
+            log::warn!("CORS config: {warning}");
+        }
+        for error in &errors {
+            log::error!("CORS config: {error}");
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Problems that make the config unusable
+    pub(crate) fn errors(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        if self.allow_credentials {
+            let wildcards = [
+                (
+                    "allowed-origins",
+                    matches!(self.allowed_origins, AllowedOrigins::Any),
+                ),
+                (
+                    "allowed-headers",
+                    matches!(self.allowed_headers, AllowedHeaders::Any),
+                ),
+                (
+                    "allowed-methods",
+                    matches!(self.allowed_methods, AllowedMethods::Any),
+                ),
+                (
+                    "expose-headers",
+                    matches!(self.expose_headers, ExposeHeaders::Any),
+                ),
+            ];
+            errors.extend(
+                wildcards
+                    .into_iter()
+                    .filter(|(_, is_wildcard)| *is_wildcard)
+                    .map(|(field, _)| ConfigError::CredentialsWithWildcard { field }),
+            );
+        }
+        errors
+    }
+
+    /// Findings that don't stop the config from working but are likely
+    /// mistakes.
+    ///
+    /// An empty origin, method or header list denies every cross-origin
+    /// request that needs it. That is almost always a misconfiguration, but
+    /// some deployments want exactly that, so it is only a warning. Likewise,
+    /// `Vary: Origin` is pointless when every origin gets the same `*`
+    /// response, but it only costs cache entries, and a `max-age` beyond
+    /// [`MAX_BROWSER_MAX_AGE`] is clamped by browsers rather than rejected.
+    /// Allowing private network access to `any` or `mirror` origins is
+    /// flagged since it exposes the private network to every website.
+    /// Credentialed configs whose header list has neither `authorization` nor
+    /// `content-type` are flagged too: logins then work from curl but fail
+    /// in the browser.
+    pub fn warnings(&self) -> Vec<ConfigError> {
+        let mut warnings = Vec::new();
+        let empty_origins = match &self.allowed_origins {
+            AllowedOrigins::Exact(origins) | AllowedOrigins::Wildcard(origins) => {
+                origins.is_empty()
+            }
+            AllowedOrigins::List(origins) => origins.is_empty(),
+            AllowedOrigins::Any
+            | AllowedOrigins::Mirror
+            | AllowedOrigins::LocalhostAny
+            | AllowedOrigins::Dynamic(_)
+            | AllowedOrigins::Shared(_) => false,
+        };
+        if empty_origins {
+            warnings.push(ConfigError::EmptyList {
+                field: "allowed-origins",
+            });
+        }
+        if matches!(&self.allowed_methods, AllowedMethods::List(methods) if methods.is_empty()) {
+            warnings.push(ConfigError::EmptyList {
+                field: "allowed-methods",
+            });
+        }
+        if self.allowed_headers.is_empty()
+            || matches!(&self.allowed_headers, AllowedHeaders::Regex(patterns) if patterns.is_empty())
+        {
+            warnings.push(ConfigError::EmptyList {
+                field: "allowed-headers",
+            });
+        }
+        if self.allow_credentials
+            && matches!(
+                &self.allowed_headers,
+                AllowedHeaders::MirrorList(headers) | AllowedHeaders::List(headers)
+                    if !headers.is_empty()
+                        && !headers.contains(&http::header::AUTHORIZATION)
+                        && !headers.contains(&http::header::CONTENT_TYPE)
+            )
+        {
+            warnings.push(ConfigError::CredentialsWithoutAuthHeaders);
+        }
+        if self.allow_private_network
+            && matches!(
+                self.allowed_origins,
+                AllowedOrigins::Any | AllowedOrigins::Mirror
+            )
+        {
+            warnings.push(ConfigError::PrivateNetworkWithAnyOrigin);
+        }
+        // with a denylist the origin is mirrored rather than answered with `*`
+        if matches!(self.allowed_origins, AllowedOrigins::Any)
+            && self.denied_origins.is_none()
+            && self.vary.0.contains(&http::header::ORIGIN)
+        {
+            warnings.push(ConfigError::VaryOriginWithAnyOrigin);
+        }
+        let longest_max_age = self
+            .max_age
+            .as_duration()
+            .into_iter()
+            .chain(self.max_age_by_origin.iter().map(|group| group.max_age))
+            .max();
+        if let Some(max_age) = longest_max_age.filter(|max_age| *max_age > MAX_BROWSER_MAX_AGE) {
+            warnings.push(ConfigError::MaxAgeTooLong(max_age));
+        }
+        warnings
+    }
+
+    /// Reject any allowed method outside of [`STANDARD_METHODS`].
+    ///
+    /// `Method` accepts any valid token as an extension method, so a typo like
+    /// `GEET` parses without complaint. Teams that only use the standard
+    /// methods can call this to catch those.
+    pub fn validate_standard_methods(&self) -> Result<(), ConfigError> {
+        let AllowedMethods::List(methods) = &self.allowed_methods else {
+            return Ok(());
+        };
+        let mut non_standard = methods
+            .iter()
+            .filter(|method| !STANDARD_METHODS.contains(method))
+            .map(|method| method.to_string())
+            .collect::<Vec<_>>();
+        if non_standard.is_empty() {
+            return Ok(());
+        }
+        non_standard.sort();
+        Err(ConfigError::NonStandardMethods(non_standard))
+    }
+
+    /// Flag regex origin patterns that look like a literal origin pasted in
+    /// as-is, such as `https://example.com`.
+    ///
+    /// An unescaped `.` matches any character, so that pattern also allows
+    /// `https://exampleXcom`. Only dots directly before a TLD-like segment
+    /// are reported, which leaves intentional ones like `.*` alone. The
+    /// result is a [warning](Severity::Warning) listing the offending
+    /// patterns from `allowed-origins`, `denied-origins` and
+    /// `max-age-by-origin`.
+    pub fn validate_origin_patterns(&self) -> Result<(), ConfigError> {
+        let lists = std::iter::once(&self.allowed_origins)
+            .chain(self.max_age_by_origin.iter().map(|group| &group.origins))
+            .filter_map(|origins| match origins {
+                AllowedOrigins::List(patterns) => Some(patterns),
+                _ => None,
+            })
+            .chain(&self.denied_origins);
+        let mut suspicious = lists
+            .flat_map(|patterns| patterns.patterns())
+            .filter(|pattern| has_unescaped_tld_dot(pattern))
+            .cloned()
+            .collect::<Vec<_>>();
+        if suspicious.is_empty() {
+            return Ok(());
+        }
+        suspicious.sort();
+        suspicious.dedup();
+        Err(ConfigError::UnescapedDots(suspicious))
+    }
+}
+
+/// Whether `pattern` has a `.` outside of an escape or character class that
+/// is followed by two or more letters and then the end of the host
+fn has_unescaped_tld_dot(pattern: &str) -> bool {
+    let mut escaped = false;
+    let mut in_class = false;
+    for (index, char) in pattern.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ']' if in_class => in_class = false,
+            _ if in_class => {}
+            '[' => in_class = true,
+            '.' => {
+                let rest = &pattern[index + 1..];
+                let label = rest
+                    .find(|char: char| !char.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                let after = &rest[label..];
+                if label >= 2
+                    && (after.is_empty() || after.starts_with([':', '/', '(', ')', '|', '$']))
+                {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr};
+
+    use super::*;
+    use crate::{ConfigBuilder, MaxAge, OriginMaxAge, Vary};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_standard_methods() {
+        let config = ConfigBuilder::new()
+            .allow_method(Method::GET)
+            .allow_method(Method::OPTIONS)
+            .build()
+            .unwrap();
+        assert!(config.validate_standard_methods().is_ok());
+
+        let config = Config {
+            allowed_methods: AllowedMethods::Mirror,
+            ..config
+        };
+        assert!(config.validate_standard_methods().is_ok());
+    }
+
+    #[test]
+    fn test_empty_lists_warn() {
+        let config = ConfigBuilder::new()
+            .allow_header(http::header::ACCEPT)
+            .build()
+            .unwrap();
+        let warnings = config.warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            warnings[0],
+            ConfigError::EmptyList {
+                field: "allowed-origins"
+            }
+        ));
+        assert!(matches!(
+            warnings[1],
+            ConfigError::EmptyList {
+                field: "allowed-methods"
+            }
+        ));
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.severity() == Severity::Warning));
+    }
+
+    #[test]
+    fn test_strictness() {
+        let deny_all = ConfigBuilder::new().build().unwrap();
+        assert!(deny_all.clone().try_into_layer(Strictness::Lenient).is_ok());
+        assert!(matches!(
+            deny_all.try_into_layer(Strictness::Strict),
+            Err(ConfigError::EmptyList { .. })
+        ));
+
+        let config = ConfigBuilder::new()
+            .allow_origin(AllowedOrigins::Any)
+            .allow_method(Method::GET)
+            .allow_header(http::header::ACCEPT)
+            .build()
+            .unwrap();
+        assert!(config.warnings().is_empty());
+        assert!(config.try_into_layer(Strictness::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_vary_origin_with_any_origin() {
+        let config = Config {
+            vary: Vary(HashSet::from([http::header::ORIGIN])),
+            ..Config::permissive()
+        };
+        assert!(matches!(
+            config.warnings()[..],
+            [ConfigError::VaryOriginWithAnyOrigin]
+        ));
+
+        let config = Config {
+            allowed_origins: AllowedOrigins::Mirror,
+            ..config
+        };
+        assert!(config.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_credentials_without_auth_headers() {
+        let config = Config {
+            allow_credentials: true,
+            allowed_origins: AllowedOrigins::exact("https://app.example"),
+            allowed_headers: AllowedHeaders::list([http::HeaderName::from_static("x-request-id")]),
+            ..Config::default()
+        };
+        assert!(matches!(
+            config.warnings()[..],
+            [ConfigError::CredentialsWithoutAuthHeaders]
+        ));
+
+        for headers in [
+            AllowedHeaders::list([http::header::AUTHORIZATION]),
+            AllowedHeaders::list([http::header::CONTENT_TYPE]),
+            AllowedHeaders::Mirror,
+        ] {
+            let config = Config {
+                allowed_headers: headers,
+                ..config.clone()
+            };
+            assert!(config.warnings().is_empty(), "{config:?}");
+        }
+        let config = Config {
+            allow_credentials: false,
+            ..config
+        };
+        assert!(config.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_private_network_with_any_origin() {
+        for origins in [AllowedOrigins::Any, AllowedOrigins::Mirror] {
+            let config = Config {
+                allow_private_network: true,
+                allowed_origins: origins,
+                ..Config::permissive()
+            };
+            assert!(matches!(
+                config.warnings()[..],
+                [ConfigError::PrivateNetworkWithAnyOrigin]
+            ));
+        }
+        let config = Config {
+            allow_private_network: true,
+            allowed_origins: AllowedOrigins::exact("https://router.example"),
+            ..Config::permissive()
+        };
+        assert!(config.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_max_age_too_long() {
+        let config = Config {
+            max_age: MaxAge::BrowserMax,
+            ..Config::permissive()
+        };
+        assert!(config.warnings().is_empty());
+
+        let config = Config {
+            max_age_by_origin: vec![OriginMaxAge {
+                origins: AllowedOrigins::exact("https://example.com"),
+                max_age: Duration::from_secs(30 * 86400),
+            }],
+            ..config
+        };
+        let warnings = config.warnings();
+        assert!(matches!(
+            warnings[..],
+            [ConfigError::MaxAgeTooLong(max_age)] if max_age == Duration::from_secs(30 * 86400)
+        ));
+        assert_eq!(
+            warnings[0].to_string(),
+            "`max-age` of 2592000s is longer than browsers cache preflights (at most 86400s)"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_everything() {
+        let config = Config {
+            allow_credentials: true,
+            allowed_origins: AllowedOrigins::Any,
+            expose_headers: ExposeHeaders::Any,
+            ..ConfigBuilder::new().build().unwrap()
+        };
+        let problems = config.validate().unwrap_err();
+        let summary = problems
+            .iter()
+            .map(|problem| (problem.severity(), problem.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    Severity::Error,
+                    "cannot combine `allow-credentials: true` with `allowed-origins: any`; \
+                     use `allowed-origins: mirror` to reflect the request instead"
+                        .to_string()
+                ),
+                (
+                    Severity::Error,
+                    "cannot combine `allow-credentials: true` with `expose-headers: any`; \
+                     list the headers to expose instead"
+                        .to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "`allowed-methods` is empty, so matching requests are always denied"
+                        .to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "`allowed-headers` is empty, so matching requests are always denied"
+                        .to_string()
+                ),
+            ]
+        );
+
+        // the single-error path stops at the first error
+        assert!(matches!(
+            CorsLayer::try_from(config),
+            Err(ConfigError::CredentialsWithWildcard {
+                field: "allowed-origins"
+            })
+        ));
+        assert!(Config::permissive().validate().is_ok());
+    }
+
+    #[test]
+    fn test_credentials_with_any_headers() {
+        let config = Config {
+            allow_credentials: true,
+            allowed_origins: AllowedOrigins::exact("https://app.example"),
+            allowed_headers: AllowedHeaders::Any,
+            ..Config::default()
+        };
+        let errors = config.errors();
+        assert!(matches!(
+            errors[..],
+            [ConfigError::CredentialsWithWildcard {
+                field: "allowed-headers"
+            }]
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "cannot combine `allow-credentials: true` with `allowed-headers: any`; \
+             with credentials browsers read `*` as a header named `*`, so use \
+             `allowed-headers: mirror` or list the headers instead"
+        );
+    }
+
+    #[test]
+    fn test_unescaped_dots() {
+        let config = |patterns: &[&str]| Config {
+            allowed_origins: AllowedOrigins::regexes(patterns).unwrap(),
+            ..Config::permissive()
+        };
+        for pattern in [
+            r"https://.*\.example\.com",
+            r"https://example\.com",
+            r"https://[a.]+\.example\.org",
+            r"https://example\.co\.uk:.*",
+        ] {
+            assert!(
+                config(&[pattern]).validate_origin_patterns().is_ok(),
+                "{pattern}"
+            );
+        }
+
+        let mut config = config(&[
+            r"https://example.com",
+            r"https://(foo|bar)\.example.org(:8080)?",
+            r"https://.*\.example\.com",
+        ]);
+        config.denied_origins = Some(
+            crate::SerdeRegexSet::new(
+                regex::RegexSet::new([r"https://evil.io|https://worse\.io"]).unwrap(),
+            )
+            .unwrap(),
+        );
+        let error = config.validate_origin_patterns().unwrap_err();
+        assert_eq!(error.severity(), Severity::Warning);
+        match error {
+            ConfigError::UnescapedDots(patterns) => assert_eq!(
+                patterns,
+                [
+                    r"https://(foo|bar)\.example.org(:8080)?",
+                    r"https://evil.io|https://worse\.io",
+                    r"https://example.com",
+                ]
+            ),
+            other => panic!("expected unescaped dots, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_non_standard_methods_are_listed() {
+        let config = Config {
+            allowed_methods: AllowedMethods::List(HashSet::from([
+                Method::GET,
+                Method::from_str("GEET").unwrap(),
+                Method::from_str("PURGE").unwrap(),
+            ])),
+            ..ConfigBuilder::new().build().unwrap()
+        };
+        match config.validate_standard_methods() {
+            Err(ConfigError::NonStandardMethods(methods)) => assert_eq!(methods, ["GEET", "PURGE"]),
+            other => panic!("expected non-standard methods, got {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_validate_and_log() {
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for Capture {
+            fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record<'_>) {
+                let message = (record.level(), record.args().to_string());
+                self.0.lock().unwrap().push(message);
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let config = Config {
+            allow_credentials: true,
+            allowed_headers: AllowedHeaders::Any,
+            ..ConfigBuilder::new().build().unwrap()
+        };
+        let errors = config.validate_and_log().unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ConfigError::CredentialsWithWildcard {
+                field: "allowed-headers"
+            }]
+        ));
+        let logged = LOGGER.0.lock().unwrap();
+        assert!(logged
+            .iter()
+            .any(|(level, message)| *level == log::Level::Warn && message.contains("empty")));
+        assert!(logged
+            .iter()
+            .any(|(level, message)| *level == log::Level::Error && message.contains("`*`")));
+    }
+}
@@ -0,0 +1,128 @@
+//! The config file format version, so older files can be migrated
+
+#[cfg(feature = "yaml")]
+use crate::{Config, ConfigError};
+
+/// The config file format this crate reads and writes
+///
+/// It only changes when a config file that used to load would be read
+/// differently, and [`Config::migrate`] then upgrades files written for
+/// earlier versions.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+pub(crate) fn current() -> u32 {
+    CONFIG_VERSION
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn is_current(version: &u32) -> bool {
+    *version == CONFIG_VERSION
+}
+
+/// Reads a version, rejecting any this crate doesn't know
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    let version = <u64 as serde::Deserialize>::deserialize(deserializer)?;
+    check(version).map_err(serde::de::Error::custom)
+}
+
+/// Like [`deserialize`], for fields that may be missing
+#[cfg(any(
+    feature = "json",
+    feature = "json5",
+    feature = "toml",
+    feature = "yaml"
+))]
+pub(crate) fn deserialize_some<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    deserialize(deserializer).map(Some)
+}
+
+#[cfg(feature = "serde")]
+fn check(version: u64) -> Result<u32, crate::ConfigError> {
+    match u32::try_from(version) {
+        Ok(version @ 1..=CONFIG_VERSION) => Ok(version),
+        _ => Err(crate::ConfigError::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl Config {
+    /// Read a config file of any known version, upgrading older shapes to
+    /// the current one. A missing `version` counts as [`CONFIG_VERSION`].
+    ///
+    /// There has only been one version so far, so this just rejects
+    /// versions this crate doesn't know, e.g. from a newer release. When the
+    /// format changes, the upgrade from each version to the next goes here,
+    /// and files keep loading.
+    ///
+    /// ```
+    /// use tower_http_cors_config::{Config, ConfigError};
+    ///
+    /// let value = serde_yaml::from_str("{version: 1, allowed-origins: any}").unwrap();
+    /// assert!(Config::migrate(value).is_ok());
+    ///
+    /// let value = serde_yaml::from_str("{version: 99, allowed-origins: any}").unwrap();
+    /// assert!(matches!(
+    ///     Config::migrate(value),
+    ///     Err(ConfigError::UnsupportedVersion(99))
+    /// ));
+    /// ```
+    pub fn migrate(value: serde_yaml::Value) -> Result<Config, ConfigError> {
+        let version = match value.get("version") {
+            Some(version) => check(serde_yaml::from_value(version.clone())?)?,
+            None => CONFIG_VERSION,
+        };
+        match version {
+            CONFIG_VERSION => Ok(serde_yaml::from_value(value)?),
+            version => Err(ConfigError::UnsupportedVersion(version.into())),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let config = serde_yaml::from_str::<Config>("version: 1").unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(serde_yaml::from_str::<Config>("{}").unwrap(), config);
+        // the current version is implied
+        assert!(!serde_yaml::to_string(&config).unwrap().contains("version"));
+
+        for yaml in ["version: 2", "version: 0"] {
+            let error = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+            assert!(
+                error.to_string().contains("unsupported config version"),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate_rejects_future_versions() {
+        let migrate = |yaml| Config::migrate(serde_yaml::from_str(yaml).unwrap());
+        assert_eq!(
+            migrate("{allowed-origins: any}").unwrap(),
+            Config {
+                allowed_origins: crate::AllowedOrigins::Any,
+                ..Config::default()
+            }
+        );
+        assert!(migrate("version: 1").is_ok());
+        assert!(matches!(
+            migrate("version: 2"),
+            Err(ConfigError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            migrate("version: latest"),
+            Err(ConfigError::Yaml(_))
+        ));
+    }
+}
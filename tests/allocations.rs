@@ -0,0 +1,65 @@
+//! Allocations made converting a `Config` into a `CorsLayer`, counted per
+//! thread so other tests running alongside don't show up
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use regex::RegexSet;
+use tower_http::cors::CorsLayer;
+use tower_http_cors_config::{AllowedOrigins, Config, SerdeRegexSet};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| {
+            let (allocations, bytes) = count.get();
+            count.set((allocations + 1, bytes + layout.size()));
+        });
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The allocations and bytes allocated while running `f`
+fn allocations(f: impl FnOnce()) -> (usize, usize) {
+    let (allocations, bytes) = ALLOCATIONS.with(Cell::get);
+    f();
+    let (after, bytes_after) = ALLOCATIONS.with(Cell::get);
+    (after - allocations, bytes_after - bytes)
+}
+
+#[test]
+fn test_converting_reuses_anchored_patterns() {
+    let patterns = (0..20)
+        .map(|tenant| format!(r"https://tenant{tenant}\.example\.com"))
+        .collect::<Vec<_>>();
+    let mut origins = None;
+    let build = allocations(|| {
+        origins = Some(SerdeRegexSet::new(RegexSet::new(&patterns).unwrap()).unwrap());
+    });
+    let config = Config {
+        allowed_origins: AllowedOrigins::List(origins.unwrap()),
+        ..Config::default()
+    };
+
+    let convert = allocations(|| drop(CorsLayer::try_from(config.clone()).unwrap()));
+    let layer = CorsLayer::try_from(config).unwrap();
+    let clone = allocations(|| drop(layer.clone()));
+    // measured: build (3105, 922480), convert (10, 2302), clone (1, 24)
+    assert!(convert.0 * 10 < build.0, "{convert:?} vs {build:?}");
+    assert!(convert.1 * 10 < build.1, "{convert:?} vs {build:?}");
+    assert!(clone.0 <= 1, "{clone:?}");
+}
@@ -0,0 +1,76 @@
+//! A config-built `CorsLayer` used as an axum layer
+
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use axum::{body::Body, routing::get, Router};
+use http::{header, Method, Request, StatusCode};
+use tower_http::cors::CorsLayer;
+use tower_http_cors_config::{AllowedMethods, AllowedOrigins, Config};
+use tower_service::Service;
+
+fn router() -> Router {
+    let config = Config {
+        allowed_origins: AllowedOrigins::exact("https://app.example"),
+        allowed_methods: AllowedMethods::list([Method::GET, Method::POST]),
+        ..Config::default()
+    };
+    Router::new()
+        .route("/", get(|| async { "hello" }))
+        .layer(CorsLayer::try_from(config).unwrap())
+}
+
+/// Poll the router once; the handler never waits, so neither does this
+fn call(request: Request<Body>) -> http::Response<Body> {
+    let mut router = router();
+    let future = pin!(router.call(request));
+    match future.poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(Ok(response)) => response,
+        Poll::Ready(Err(error)) => match error {},
+        Poll::Pending => panic!("the router should respond without waiting"),
+    }
+}
+
+#[test]
+fn test_axum_simple_request() {
+    let response = call(
+        Request::get("/")
+            .header(header::ORIGIN, "https://app.example")
+            .body(Body::empty())
+            .unwrap(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://app.example"
+    );
+}
+
+#[test]
+fn test_axum_preflight() {
+    let response = call(
+        Request::options("/")
+            .header(header::ORIGIN, "https://app.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let methods = response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS]
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("POST"), "{methods}");
+
+    let response = call(
+        Request::get("/")
+            .header(header::ORIGIN, "https://evil.example")
+            .body(Body::empty())
+            .unwrap(),
+    );
+    assert!(!response
+        .headers()
+        .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}